@@ -0,0 +1,191 @@
+//! Packed binary-coded decimal helpers.
+//!
+//! Values are packed two digits per byte, high nibble first ($42 is 42).
+//! Multi-byte numbers are stored least significant byte first, the same
+//! order guest code walks them with ADC/SBC.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BcdError {
+    /// The binary value doesn't fit in two decimal digits.
+    OutOfRange(u8),
+    /// One of the nibbles is above 9.
+    InvalidDigit(u8),
+    /// Multi-byte operands aren't the same number of bytes.
+    LengthMismatch,
+}
+
+/// True when both nibbles are decimal digits.
+pub fn is_valid(value: u8) -> bool {
+    value & 0x0F <= 9 && value >> 4 <= 9
+}
+
+/// True when every byte is valid packed BCD.
+pub fn is_valid_slice(value: &[u8]) -> bool {
+    value.iter().all(|&b| is_valid(b))
+}
+
+pub fn to_bcd(value: u8) -> Result<u8, BcdError> {
+    if value > 99 {
+        return Err(BcdError::OutOfRange(value));
+    }
+    Ok(((value / 10) << 4) | (value % 10))
+}
+
+pub fn from_bcd(value: u8) -> Result<u8, BcdError> {
+    if !is_valid(value) {
+        return Err(BcdError::InvalidDigit(value));
+    }
+    Ok((value >> 4) * 10 + (value & 0x0F))
+}
+
+/// Adds two packed bytes plus carry the way an NMOS 6502 does in decimal
+/// mode, returning the result and the carry out. Invalid digits produce
+/// the same (meaningless) results as the hardware adjustment.
+pub fn add_u8(a: u8, b: u8, carry: bool) -> (u8, bool) {
+    let (lo, hi) = add_nibbles(a, b, carry);
+    let hi = if hi > 9 { hi + 6 } else { hi };
+    (((hi << 4) | (lo & 0x0F)) as u8, hi > 0x0F)
+}
+
+/// Subtracts `b` and the borrow (`!carry`) from `a` the way an NMOS 6502
+/// does in decimal mode, returning the result and the carry out (set when
+/// no borrow occurred).
+pub fn sub_u8(a: u8, b: u8, carry: bool) -> (u8, bool) {
+    let borrow = if carry { 0 } else { 1 };
+    let mut lo = (a & 0x0F) as i16 - (b & 0x0F) as i16 - borrow;
+    let mut hi = (a >> 4) as i16 - (b >> 4) as i16;
+    if lo < 0 {
+        lo -= 6;
+        hi -= 1;
+    }
+    if hi < 0 {
+        hi -= 6;
+    }
+    let result = ((hi << 4) | (lo & 0x0F)) as u8;
+    (result, a as i16 - b as i16 - borrow >= 0)
+}
+
+/// The decimal add before the high digit is adjusted. NMOS parts derive
+/// N and V from this intermediate value rather than the final result.
+pub fn add_intermediate(a: u8, b: u8, carry: bool) -> u8 {
+    let (lo, hi) = add_nibbles(a, b, carry);
+    ((hi << 4) | (lo & 0x0F)) as u8
+}
+
+/// Adds `rhs` into `lhs` as multi-byte packed BCD and returns the final
+/// carry. Fails with `LengthMismatch` unless the slices are the same length.
+pub fn add(lhs: &mut [u8], rhs: &[u8], carry: bool) -> Result<bool, BcdError> {
+    check_operands(lhs, rhs)?;
+    let mut carry = carry;
+    for (l, &r) in lhs.iter_mut().zip(rhs) {
+        let (value, c) = add_u8(*l, r, carry);
+        *l = value;
+        carry = c;
+    }
+    Ok(carry)
+}
+
+/// Subtracts `rhs` from `lhs` as multi-byte packed BCD and returns the final
+/// carry (clear when the result borrowed). Fails with `LengthMismatch`
+/// unless the slices are the same length.
+pub fn sub(lhs: &mut [u8], rhs: &[u8], carry: bool) -> Result<bool, BcdError> {
+    check_operands(lhs, rhs)?;
+    let mut carry = carry;
+    for (l, &r) in lhs.iter_mut().zip(rhs) {
+        let (value, c) = sub_u8(*l, r, carry);
+        *l = value;
+        carry = c;
+    }
+    Ok(carry)
+}
+
+fn add_nibbles(a: u8, b: u8, carry: bool) -> (u16, u16) {
+    let mut lo = (a & 0x0F) as u16 + (b & 0x0F) as u16 + carry as u16;
+    if lo > 9 {
+        lo += 6;
+    }
+    let hi = (a >> 4) as u16 + (b >> 4) as u16 + (lo > 0x0F) as u16;
+    (lo, hi)
+}
+
+fn check_operands(lhs: &[u8], rhs: &[u8]) -> Result<(), BcdError> {
+    if lhs.len() != rhs.len() {
+        return Err(BcdError::LengthMismatch);
+    }
+    for &b in lhs.iter().chain(rhs) {
+        if !is_valid(b) {
+            return Err(BcdError::InvalidDigit(b));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn every_value_round_trips() {
+        for value in 0..100u8 {
+            let packed = to_bcd(value).unwrap();
+            assert!(is_valid(packed), "{} packed to ${:02X}", value, packed);
+            assert_eq!(from_bcd(packed), Ok(value));
+        }
+        assert_eq!(to_bcd(100), Err(BcdError::OutOfRange(100)));
+        assert_eq!(to_bcd(0xFF), Err(BcdError::OutOfRange(0xFF)));
+    }
+    
+    #[test]
+    fn invalid_nibbles_are_rejected() {
+        for value in 0..=0xFFu8 {
+            let valid = value & 0x0F <= 9 && value >> 4 <= 9;
+            assert_eq!(is_valid(value), valid, "${:02X}", value);
+            if !valid {
+                assert_eq!(from_bcd(value), Err(BcdError::InvalidDigit(value)));
+            }
+        }
+        assert!(is_valid_slice(&[0x00, 0x99, 0x42]));
+        assert!(!is_valid_slice(&[0x00, 0x9A, 0x42]));
+    }
+    
+    #[test]
+    fn single_byte_add_and_sub() {
+        assert_eq!(add_u8(0x09, 0x01, false), (0x10, false));
+        assert_eq!(add_u8(0x99, 0x01, false), (0x00, true));
+        assert_eq!(add_u8(0x45, 0x54, true), (0x00, true));
+        assert_eq!(sub_u8(0x10, 0x01, true), (0x09, true));
+        assert_eq!(sub_u8(0x00, 0x01, true), (0x99, false));
+        assert_eq!(sub_u8(0x50, 0x25, false), (0x24, true));
+    }
+    
+    #[test]
+    fn multi_byte_carries_ripple() {
+        // 9999 + 1 = 10000, least significant byte first.
+        let mut lhs = [0x99, 0x99, 0x00];
+        assert_eq!(add(&mut lhs, &[0x01, 0x00, 0x00], false), Ok(false));
+        assert_eq!(lhs, [0x00, 0x00, 0x01]);
+        
+        let mut lhs = [0x99, 0x99];
+        assert_eq!(add(&mut lhs, &[0x01, 0x00], false), Ok(true));
+        assert_eq!(lhs, [0x00, 0x00]);
+        
+        // 10000 - 1 = 9999
+        let mut lhs = [0x00, 0x00, 0x01];
+        assert_eq!(sub(&mut lhs, &[0x01, 0x00, 0x00], true), Ok(true));
+        assert_eq!(lhs, [0x99, 0x99, 0x00]);
+        
+        // 0 - 1 borrows out of the top byte.
+        let mut lhs = [0x00, 0x00];
+        assert_eq!(sub(&mut lhs, &[0x01, 0x00], true), Ok(false));
+        assert_eq!(lhs, [0x99, 0x99]);
+    }
+    
+    #[test]
+    fn multi_byte_operands_are_checked() {
+        let mut lhs = [0x12, 0x34];
+        assert_eq!(add(&mut lhs, &[0x01], false), Err(BcdError::LengthMismatch));
+        assert_eq!(sub(&mut lhs, &[0x01, 0x02, 0x03], true), Err(BcdError::LengthMismatch));
+        assert_eq!(add(&mut lhs, &[0x0A, 0x00], false), Err(BcdError::InvalidDigit(0x0A)));
+        assert_eq!(lhs, [0x12, 0x34]);
+    }
+}
@@ -6,11 +6,16 @@ pub trait Backend: fmt::Debug {
     fn write(&mut self, addr: u16, value: u8);
 }
 
-#[derive(Debug)]
 pub struct Bus {
     entries: Vec<BusEntry>,
 }
 
+impl Default for Bus {
+    fn default() -> Bus {
+        Bus::new()
+    }
+}
+
 impl Bus {
     pub fn new() -> Bus {
         Bus { entries: vec![] }
@@ -25,6 +30,13 @@ impl Bus {
         entry.read(addr)
     }
     
+    /// Reads `addr` without panicking when nothing is mapped there.
+    pub fn peek(&self, addr: u16) -> Option<u8> {
+        self.entries.iter()
+            .find(|entry| entry.start <= addr && addr <= entry.end)
+            .map(|entry| entry.read(addr))
+    }
+    
     pub fn read_u16(&self, addr: u16) -> u16 {
         let entry = self.backend(addr);
         if addr == entry.end {
@@ -49,6 +61,77 @@ impl Bus {
         entry.write(addr + 1, (value >> 8) as u8);
     }
     
+    /// Writes `data` starting at `addr`, wrapping past $FFFF.
+    pub fn load(&mut self, addr: u16, data: &[u8]) {
+        for (i, &value) in data.iter().enumerate() {
+            self.write(addr.wrapping_add(i as u16), value);
+        }
+    }
+    
+    pub fn read_zp(&self, addr: u8) -> u8 {
+        self.read(addr as u16)
+    }
+    
+    pub fn write_zp(&mut self, addr: u8, value: u8) {
+        self.write(addr as u16, value);
+    }
+    
+    /// Reads a little-endian pointer from the zero page. Like the CPU, the
+    /// high byte of a pointer at $FF comes from $00.
+    pub fn read_zp_ptr(&self, addr: u8) -> u16 {
+        self.read_zp(addr) as u16 | ((self.read_zp(addr.wrapping_add(1)) as u16) << 8)
+    }
+    
+    /// Reads the byte `offset` bytes past the pointer stored at `zp`.
+    pub fn read_via_zp_ptr(&self, zp: u8, offset: u16) -> u8 {
+        self.read(self.read_zp_ptr(zp).wrapping_add(offset))
+    }
+    
+    /// Reads bytes from `addr` up to (not including) the first zero byte,
+    /// giving up after `max` bytes.
+    pub fn read_cstr(&self, addr: u16, max: usize) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut addr = addr;
+        while bytes.len() < max {
+            let value = self.read(addr);
+            if value == 0 {
+                break;
+            }
+            bytes.push(value);
+            addr = addr.wrapping_add(1);
+        }
+        bytes
+    }
+    
+    /// `read_zp_ptr`, but `None` if either pointer byte is unmapped.
+    pub fn peek_zp_ptr(&self, addr: u8) -> Option<u16> {
+        let lo = self.peek(addr as u16)?;
+        let hi = self.peek(addr.wrapping_add(1) as u16)?;
+        Some(lo as u16 | ((hi as u16) << 8))
+    }
+    
+    /// `read_via_zp_ptr`, but `None` if the pointer or its target is
+    /// unmapped.
+    pub fn peek_via_zp_ptr(&self, zp: u8, offset: u16) -> Option<u8> {
+        self.peek(self.peek_zp_ptr(zp)?.wrapping_add(offset))
+    }
+    
+    /// `read_cstr`, but `None` if the string runs into unmapped memory
+    /// before its terminator or `max`.
+    pub fn peek_cstr(&self, addr: u16, max: usize) -> Option<Vec<u8>> {
+        let mut bytes = vec![];
+        let mut addr = addr;
+        while bytes.len() < max {
+            let value = self.peek(addr)?;
+            if value == 0 {
+                break;
+            }
+            bytes.push(value);
+            addr = addr.wrapping_add(1);
+        }
+        Some(bytes)
+    }
+    
     pub fn clear(&mut self) {
         self.entries.clear();
     }
@@ -72,25 +155,39 @@ impl Bus {
     }
 }
 
-#[derive(Debug)]
+impl fmt::Debug for Bus {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(&self.entries).finish()
+    }
+}
+
 pub struct BusEntry {
-    backend: Box<Backend>,
+    backend: Box<dyn Backend>,
     name: String,
     start: u16,
+    /// Last mapped address, inclusive.
     end: u16,
 }
 
 impl BusEntry {
-    pub fn new(backend: Box<Backend>, name: String, start: u16) -> BusEntry {
+    pub fn new(backend: Box<dyn Backend>, name: String, start: u16) -> BusEntry {
         let size = backend.size();
+        assert!(size > 0, "Backend `{}` has no addresses", name);
+        let end = start.checked_add(size - 1).unwrap_or_else(|| {
+            panic!("Backend `{}` at ${:04X} runs past $FFFF", name, start)
+        });
         BusEntry {
-            backend: backend,
-            name: name,
-            start: start,
-            end: start + size,
+            backend,
+            name,
+            start,
+            end,
         }
     }
     
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
     pub fn read(&self, addr: u16) -> u8 {
         self.backend.read(addr - self.start)
     }
@@ -100,3 +197,125 @@ impl BusEntry {
     }
 }
 
+impl fmt::Debug for BusEntry {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{} ${:04X}-${:04X}", self.name, self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use memory::ram::Ram;
+    
+    fn ram_bus() -> Bus {
+        let mut bus = Bus::new();
+        bus.attach(Ram::new_entry());
+        bus
+    }
+    
+    /// Each node is a next pointer (zero ends the list) followed by a
+    /// zero-terminated name.
+    fn write_node(bus: &mut Bus, addr: u16, next: u16, name: &str) {
+        bus.write_u16(addr, next);
+        bus.load(addr + 2, name.as_bytes());
+        bus.write(addr + 2 + name.len() as u16, 0);
+    }
+    
+    #[test]
+    fn walk_linked_list_in_guest_ram() {
+        let mut bus = ram_bus();
+        write_node(&mut bus, 0x1000, 0x2345, "first");
+        write_node(&mut bus, 0x2345, 0x0400, "second");
+        write_node(&mut bus, 0x0400, 0x0000, "third");
+        bus.write_zp(0xFF, 0x00);
+        bus.write_zp(0x00, 0x10);
+        
+        // Walk with the cursor at $FF so its high byte comes from $00.
+        let mut names = vec![];
+        while bus.read_zp_ptr(0xFF) != 0 {
+            let node = bus.read_zp_ptr(0xFF);
+            names.push(String::from_utf8(bus.read_cstr(node + 2, 16)).unwrap());
+            let lo = bus.read_via_zp_ptr(0xFF, 0);
+            let hi = bus.read_via_zp_ptr(0xFF, 1);
+            bus.write_zp(0xFF, lo);
+            bus.write_zp(0x00, hi);
+        }
+        assert_eq!(names, ["first", "second", "third"]);
+    }
+    
+    #[test]
+    fn read_cstr_stops_at_max() {
+        let mut bus = ram_bus();
+        bus.load(0x0300, b"abcdef\0");
+        assert_eq!(bus.read_cstr(0x0300, 16), b"abcdef");
+        assert_eq!(bus.read_cstr(0x0300, 3), b"abc");
+        assert_eq!(bus.read_cstr(0x0306, 16), b"");
+    }
+    
+    #[test]
+    fn peek_helpers_report_unmapped_memory() {
+        let mut bus = ram_bus();
+        bus.write_u16(0x0010, 0x0300);
+        bus.load(0x0300, b"hi\0");
+        assert_eq!(bus.peek_zp_ptr(0x10), Some(0x0300));
+        assert_eq!(bus.peek_via_zp_ptr(0x10, 1), Some(b'i'));
+        assert_eq!(bus.peek_cstr(0x0300, 16), Some(b"hi".to_vec()));
+        
+        // The pointer leads past the end of RAM.
+        bus.write_u16(0x0010, 0x7FFF);
+        assert_eq!(bus.peek_via_zp_ptr(0x10, 0), Some(0));
+        assert_eq!(bus.peek_via_zp_ptr(0x10, 1), None);
+        bus.load(0x7FFE, b"ab");
+        assert_eq!(bus.peek_cstr(0x7FFE, 16), None);
+        assert_eq!(bus.peek_cstr(0x7FFE, 2), Some(b"ab".to_vec()));
+        
+        let empty = Bus::new();
+        assert_eq!(empty.peek_zp_ptr(0x10), None);
+        assert_eq!(empty.peek_cstr(0x0000, 4), None);
+    }
+    
+    #[derive(Debug)]
+    struct Bytes(Vec<u8>);
+    
+    impl Backend for Bytes {
+        fn size(&self) -> u16 {
+            self.0.len() as u16
+        }
+        
+        fn read(&self, addr: u16) -> u8 {
+            self.0[addr as usize]
+        }
+        
+        fn write(&mut self, addr: u16, value: u8) {
+            self.0[addr as usize] = value;
+        }
+    }
+    
+    fn bytes(name: &str, start: u16, size: usize) -> BusEntry {
+        BusEntry::new(Box::new(Bytes(vec![0; size])), name.into(), start)
+    }
+    
+    #[test]
+    fn last_byte_of_each_region() {
+        let mut bus = ram_bus();
+        bus.attach(bytes("IO", 0x8000, 0x100));
+        bus.attach(bytes("ONE", 0x9000, 1));
+        bus.attach(bytes("TOP", 0xFFF0, 0x10));
+        for &(first, last) in &[(0x0000, 0x7FFF), (0x8000, 0x80FF), (0x9000, 0x9000), (0xFFF0, 0xFFFF)] {
+            bus.write(first, 0x11);
+            bus.write(last, 0x22);
+            assert_eq!(bus.read(last), 0x22, "${:04X}", last);
+            assert_eq!(bus.read(first), if first == last { 0x22 } else { 0x11 }, "${:04X}", first);
+        }
+        assert_eq!(bus.peek(0x8100), None);
+        assert_eq!(bus.peek(0x9001), None);
+        assert_eq!(format!("{:?}", bus), "[RAM $0000-$7FFF, IO $8000-$80FF, ONE $9000-$9000, TOP $FFF0-$FFFF]");
+    }
+    
+    #[test]
+    #[should_panic(expected = "Backend `BIG` at $FFF0 runs past $FFFF")]
+    fn region_past_top_of_memory() {
+        bytes("BIG", 0xFFF0, 0x11);
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// A CPU clock frequency, kept as an exact ratio of hertz so crystal-derived
+/// rates like the NTSC colorburst divisions don't drift.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClockRate {
+    num: u64,
+    den: u64,
+}
+
+impl ClockRate {
+    /// Commodore 64 NTSC, 14.31818 MHz / 14 (~1.023 MHz).
+    pub const NTSC: ClockRate = ClockRate { num: 11_250_000, den: 11 };
+    /// Commodore 64 PAL, 17.734475 MHz / 18 (~0.985 MHz).
+    pub const PAL: ClockRate = ClockRate { num: 17_734_475, den: 18 };
+    /// Apple II nominal 1.023 MHz.
+    pub const APPLE_II: ClockRate = ClockRate { num: 1_023_000, den: 1 };
+    /// NES NTSC 2A03, 21.477272 MHz / 12 (~1.79 MHz).
+    pub const NES: ClockRate = ClockRate { num: 19_687_500, den: 11 };
+    /// The 8 MHz common on modern W65C02S boards.
+    pub const MHZ_8: ClockRate = ClockRate { num: 8_000_000, den: 1 };
+    
+    pub fn from_hz(hz: u64) -> ClockRate {
+        ClockRate::from_ratio(hz, 1)
+    }
+    
+    /// A rate of `num / den` hertz.
+    pub fn from_ratio(num: u64, den: u64) -> ClockRate {
+        assert!(num != 0 && den != 0, "Clock rate must be non-zero");
+        let divisor = gcd(num, den);
+        ClockRate {
+            num: num / divisor,
+            den: den / divisor,
+        }
+    }
+    
+    pub fn hz(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+    
+    /// Time taken by `cycles` cycles, rounded up to the next nanosecond so
+    /// converting back with `duration_to_cycles` gives the same count.
+    pub fn cycles_to_duration(&self, cycles: u64) -> Duration {
+        let scaled = cycles as u128 * self.den as u128 * NANOS_PER_SEC;
+        let nanos = scaled.div_ceil(self.num as u128);
+        Duration::new(
+            (nanos / NANOS_PER_SEC) as u64,
+            (nanos % NANOS_PER_SEC) as u32,
+        )
+    }
+    
+    /// Whole cycles completed within `duration`.
+    pub fn duration_to_cycles(&self, duration: Duration) -> u64 {
+        let nanos = duration.as_secs() as u128 * NANOS_PER_SEC + duration.subsec_nanos() as u128;
+        (nanos * self.num as u128 / (self.den as u128 * NANOS_PER_SEC)) as u64
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const PRESETS: &[ClockRate] = &[
+        ClockRate::NTSC,
+        ClockRate::PAL,
+        ClockRate::APPLE_II,
+        ClockRate::NES,
+        ClockRate::MHZ_8,
+    ];
+    
+    #[test]
+    fn cycles_round_trip_at_every_preset() {
+        for rate in PRESETS {
+            for &cycles in &[0, 1, 2, 3, 7, 11, 18, 1_000, 1_000_003, 29_780, 17_734_475, 3_000_000_001] {
+                let duration = rate.cycles_to_duration(cycles);
+                assert_eq!(rate.duration_to_cycles(duration), cycles, "{:?} {} cycles", rate, cycles);
+                // One nanosecond less hasn't finished the last cycle.
+                if cycles > 0 {
+                    let short = duration - Duration::from_nanos(1);
+                    assert_eq!(rate.duration_to_cycles(short), cycles - 1, "{:?} {} cycles", rate, cycles);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn durations_that_dont_divide_evenly() {
+        let rate = ClockRate::from_hz(3);
+        assert_eq!(rate.cycles_to_duration(1), Duration::from_nanos(333_333_334));
+        assert_eq!(rate.cycles_to_duration(3), Duration::from_secs(1));
+        assert_eq!(rate.duration_to_cycles(Duration::from_nanos(999_999_999)), 2);
+        assert_eq!(rate.duration_to_cycles(Duration::from_secs(1)), 3);
+        
+        // A PAL frame is 19656 cycles, which isn't a whole number of nanoseconds.
+        let frame = ClockRate::PAL.cycles_to_duration(19_656);
+        assert_eq!(frame, Duration::from_nanos(19_950_295));
+        assert_eq!(ClockRate::PAL.duration_to_cycles(frame), 19_656);
+        assert_eq!(ClockRate::PAL.duration_to_cycles(Duration::from_millis(20)), 19_704);
+    }
+    
+    #[test]
+    fn ratios_are_reduced() {
+        assert_eq!(ClockRate::from_ratio(2_046_000, 2), ClockRate::APPLE_II);
+        assert_eq!(ClockRate::from_ratio(236_250_000, 132), ClockRate::NES);
+        assert_eq!(ClockRate::from_hz(8_000_000), ClockRate::MHZ_8);
+        assert!((ClockRate::NTSC.hz() - 1_022_727.27).abs() < 0.01);
+    }
+}
@@ -1,10 +1,16 @@
+use bcd;
 use bus;
+use clock::ClockRate;
 use instruction::Instruction;
-use opcode::OpCode;
-use std::num::wrapping::OverflowingOps;
+use opcode::{OpCode, Variant};
+use std::fmt;
+use std::time::Duration;
 
 pub const STACK_BASE: u16 = 0x0100;
 
+/// Cycles taken to enter an IRQ or NMI handler, the same as BRK.
+const INTERRUPT_CYCLES: u64 = 7;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum StatusBit {
     Carry = 0,
@@ -12,11 +18,19 @@ pub enum StatusBit {
     Interrupt = 2,
     Decimal = 3,
     Break = 4,
-    Overflow = 5,
-    Negative = 6,
+    Overflow = 6,
+    Negative = 7,
+}
+
+/// What happens after a BRK hook returns.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BrkAction {
+    /// Push the return address and status and jump through $FFFE as usual.
+    Dispatch,
+    /// The host handled it; carry on after the signature byte.
+    Resume,
 }
 
-#[derive(Debug)]
 pub struct Cpu {
     pc: u16,
     ac: u8,
@@ -24,19 +38,100 @@ pub struct Cpu {
     y: u8,
     sp: u8,
     sr: u8,
+    /// Set by the KIL opcodes and STP; only `reset` clears it.
+    halted: bool,
+    /// Set alongside `halted` by the KIL opcodes only.
+    jammed: bool,
+    /// Set by WAI until the next interrupt.
+    waiting: bool,
+    variant: Variant,
+    /// Base cycle counts of every instruction and interrupt so far.
+    cycles: u64,
+    instructions: u64,
+    clock: Option<ClockRate>,
     bus: bus::Bus,
+    brk_hook: Option<BrkHook>,
+}
+
+type BrkHook = Box<dyn FnMut(&mut Cpu, u8) -> BrkAction>;
+
+impl Default for Cpu {
+    fn default() -> Cpu {
+        Cpu::new()
+    }
+}
+
+impl fmt::Debug for Cpu {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use self::StatusBit::*;
+        write!(
+            fmt,
+            "Cpu {{ pc: ${:04X}, ac: ${:02X}, x: ${:02X}, y: ${:02X}, sp: ${:02X}, sr: ",
+            self.pc, self.ac, self.x, self.y, self.sp,
+        )?;
+        let flags = [
+            (Some(Negative), 'N'),
+            (Some(Overflow), 'V'),
+            (None, '-'),
+            (Some(Break), 'B'),
+            (Some(Decimal), 'D'),
+            (Some(Interrupt), 'I'),
+            (Some(Zero), 'Z'),
+            (Some(Carry), 'C'),
+        ];
+        for &(bit, letter) in &flags {
+            let letter = match bit {
+                Some(bit) if !self.status(bit) => letter.to_ascii_lowercase(),
+                _ => letter,
+            };
+            write!(fmt, "{}", letter)?;
+        }
+        let state = if self.jammed {
+            "jammed"
+        } else if self.halted {
+            "stopped"
+        } else if self.waiting {
+            "waiting"
+        } else {
+            "running"
+        };
+        write!(fmt, ", state: {}", state)?;
+        write!(fmt, ", cycles: {}, instructions: {}", self.cycles, self.instructions)?;
+        match self.peek_instruction() {
+            Some(instruction) => write!(fmt, ", next: {:?}", instruction)?,
+            None => write!(fmt, ", next: ???")?,
+        }
+        write!(fmt, ", bus: {:?} }}", self.bus)
+    }
 }
 
 impl Cpu {
     pub fn new() -> Cpu {
+        Cpu::with_variant(Variant::Nmos)
+    }
+    
+    pub fn new_65c02() -> Cpu {
+        Cpu::with_variant(Variant::Cmos)
+    }
+    
+    pub fn with_variant(variant: Variant) -> Cpu {
         Cpu {
             pc: 0,
             ac: 0,
             x: 0,
             y: 0,
             sp: 0,
-            sr: 0,
+            // Bit 5 isn't wired to anything and always reads as set
+            sr: 0x20,
+            halted: false,
+            jammed: false,
+            waiting: false,
+            variant,
+            cycles: 0,
+            instructions: 0,
+            clock: None,
             bus: bus::Bus::new(),
+            brk_hook: None,
         }
     }
     
@@ -44,17 +139,151 @@ impl Cpu {
         self.bus.attach(entry);
     }
     
+    pub fn bus(&self) -> &bus::Bus {
+        &self.bus
+    }
+    
+    pub fn bus_mut(&mut self) -> &mut bus::Bus {
+        &mut self.bus
+    }
+    
+    /// Installs a hook called with the signature byte of every BRK, before
+    /// the interrupt is taken. Returning `BrkAction::Resume` skips the
+    /// interrupt entirely, so host code can service guest "system calls".
+    pub fn set_brk_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&mut Cpu, u8) -> BrkAction + 'static,
+    {
+        self.brk_hook = Some(Box::new(hook));
+    }
+    
+    pub fn clear_brk_hook(&mut self) {
+        self.brk_hook = None;
+    }
+    
     pub fn reset(&mut self) {
         self.pc = self.bus.read_u16(0xFFFC);
-        self.sr = 0x34;
+        self.sp = 0xFF;
+        self.sr = 0x24;
+        self.halted = false;
+        self.jammed = false;
+        self.waiting = false;
+    }
+    
+    /// Signals a non-maskable interrupt, entering the handler at $FFFA
+    /// regardless of the I flag.
+    pub fn nmi(&mut self) {
+        if self.halted {
+            return;
+        }
+        let pc = self.pc;
+        let sr = self.sr & !0x10;
+        self.interrupt(pc, sr, 0xFFFA);
+        self.cycles += INTERRUPT_CYCLES;
+    }
+    
+    /// Signals a maskable interrupt, entering the handler at $FFFE unless the
+    /// I flag is set. A masked IRQ still ends a WAI, which then carries on
+    /// with the next instruction.
+    pub fn irq(&mut self) {
+        if self.halted {
+            return;
+        }
+        if self.status(StatusBit::Interrupt) {
+            self.waiting = false;
+            return;
+        }
+        let pc = self.pc;
+        let sr = self.sr & !0x10;
+        self.interrupt(pc, sr, 0xFFFE);
+        self.cycles += INTERRUPT_CYCLES;
     }
     
+    /// Executes one instruction. Does nothing once the CPU has jammed or
+    /// stopped, or while it waits for an interrupt.
     pub fn step(&mut self) {
+        if self.halted || self.waiting {
+            return;
+        }
         let instruction = self.current_instruction();
-        self.pc += instruction.opcode.bytes as u16;
+        self.pc = self.pc.wrapping_add(instruction.opcode.bytes as u16);
+        self.cycles += instruction.opcode.cycles as u64;
+        self.instructions += 1;
         self.execute(&instruction);
     }
     
+    /// Cycles run so far, from each instruction's base count without page
+    /// crossing or branch penalties. Reset doesn't clear it.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+    
+    /// Instructions run so far, counting each `step` that executed one.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+    
+    /// The rate `elapsed` converts cycles at. No rate is set by default.
+    pub fn set_clock_rate(&mut self, rate: Option<ClockRate>) {
+        self.clock = rate;
+    }
+    
+    pub fn clock_rate(&self) -> Option<ClockRate> {
+        self.clock
+    }
+    
+    /// Emulated time taken by `cycles`, if a clock rate is set.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.clock.map(|rate| rate.cycles_to_duration(self.cycles))
+    }
+    
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+    
+    pub fn ac(&self) -> u8 {
+        self.ac
+    }
+    
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+    
+    pub fn y(&self) -> u8 {
+        self.y
+    }
+    
+    pub fn sp(&self) -> u8 {
+        self.sp
+    }
+    
+    pub fn sr(&self) -> u8 {
+        self.sr
+    }
+    
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+    
+    /// True after either a KIL jam or STP, until the next reset.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+    
+    /// True after one of the NMOS KIL opcodes locked the CPU up.
+    pub fn is_jammed(&self) -> bool {
+        self.jammed
+    }
+    
+    /// True after the 65C02 STP instruction, but not after a KIL jam.
+    pub fn is_stopped(&self) -> bool {
+        self.halted && !self.jammed
+    }
+    
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+    
     pub fn status(&self, bit: StatusBit) -> bool {
         self.status_u8(bit) == 1
     }
@@ -71,8 +300,14 @@ impl Cpu {
         use opcode::OpId::*;
         let op: fn(&mut Cpu, &Instruction) = match instruction.opcode.id {
             ADC => Cpu::adc,
+            ALR => Cpu::alr,
+            ANC => Cpu::anc,
             AND => Cpu::and,
+            ARR => Cpu::arr,
             ASL => Cpu::asl,
+            AXS => Cpu::axs,
+            BBR => Cpu::bbr,
+            BBS => Cpu::bbs,
             BCC => Cpu::bcc,
             BCS => Cpu::bcs,
             BEQ => Cpu::beq,
@@ -80,17 +315,73 @@ impl Cpu {
             BMI => Cpu::bmi,
             BNE => Cpu::bne,
             BPL => Cpu::bpl,
+            BRA => Cpu::bra,
             BRK => Cpu::brk,
+            BVC => Cpu::bvc,
+            BVS => Cpu::bvs,
             CLC => Cpu::clc,
             CLD => Cpu::cld,
             CLI => Cpu::cli,
+            CLV => Cpu::clv,
             CMP => Cpu::cmp,
             CPX => Cpu::cpx,
             CPY => Cpu::cpy,
+            DCP => Cpu::dcp,
             DEC => Cpu::dec,
             DEX => Cpu::dex,
             DEY => Cpu::dey,
+            EOR => Cpu::eor,
+            INC => Cpu::inc,
+            INX => Cpu::inx,
+            INY => Cpu::iny,
+            ISC => Cpu::isc,
             JMP => Cpu::jmp,
+            JSR => Cpu::jsr,
+            KIL => Cpu::kil,
+            LAX => Cpu::lax,
+            LDA => Cpu::lda,
+            LDX => Cpu::ldx,
+            LDY => Cpu::ldy,
+            LSR => Cpu::lsr,
+            NOP => Cpu::nop,
+            ORA => Cpu::ora,
+            PHA => Cpu::pha,
+            PHP => Cpu::php,
+            PHX => Cpu::phx,
+            PHY => Cpu::phy,
+            PLA => Cpu::pla,
+            PLP => Cpu::plp,
+            PLX => Cpu::plx,
+            PLY => Cpu::ply,
+            RLA => Cpu::rla,
+            RMB => Cpu::rmb,
+            ROL => Cpu::rol,
+            ROR => Cpu::ror,
+            RRA => Cpu::rra,
+            RTI => Cpu::rti,
+            RTS => Cpu::rts,
+            SAX => Cpu::sax,
+            SBC => Cpu::sbc,
+            SEC => Cpu::sec,
+            SED => Cpu::sed,
+            SEI => Cpu::sei,
+            SLO => Cpu::slo,
+            SMB => Cpu::smb,
+            SRE => Cpu::sre,
+            STA => Cpu::sta,
+            STP => Cpu::stp,
+            STX => Cpu::stx,
+            STY => Cpu::sty,
+            STZ => Cpu::stz,
+            TAX => Cpu::tax,
+            TAY => Cpu::tay,
+            TRB => Cpu::trb,
+            TSB => Cpu::tsb,
+            TSX => Cpu::tsx,
+            TXA => Cpu::txa,
+            TXS => Cpu::txs,
+            TYA => Cpu::tya,
+            WAI => Cpu::wai,
             _ => panic!("{:?} is not yet implemented", instruction.opcode.id),
         };
         
@@ -99,21 +390,36 @@ impl Cpu {
     
     fn current_instruction(&self) -> Instruction {
         let code = self.bus.read(self.pc);
-        let opcode = match OpCode::get(code) {
-            Some(opcode) => opcode,
-            invalid => {
-                invalid.expect(&format!("Invalid opcode at {:#x}: {:#x}", self.pc, code));
-                unreachable!()
-            },
-        };
+        let opcode = OpCode::get_for(self.variant, code).unwrap_or_else(|| {
+            panic!("Invalid opcode at {:#x}: {:#x}", self.pc, code)
+        });
+        if !opcode.stable {
+            panic!("Unsupported unstable opcode at {:#x}: {:#x} ({:?})", self.pc, code, opcode.id);
+        }
+        // Operand bytes are fetched one at a time so they can wrap past $FFFF
+        let lo = || self.bus.read(self.pc.wrapping_add(1)) as u16;
+        let hi = || self.bus.read(self.pc.wrapping_add(2)) as u16;
         match opcode.bytes {
-            1 => Instruction { opcode: opcode, operand: 0 },
-            2 => Instruction { opcode: opcode, operand: self.bus.read(self.pc + 1) as u16 },
-            3 => Instruction { opcode: opcode, operand: self.bus.read_u16(self.pc + 1) },
+            1 => Instruction { opcode, operand: 0 },
+            2 => Instruction { opcode, operand: lo() },
+            3 => Instruction { opcode, operand: lo() | (hi() << 8) },
             _ => unreachable!(),
         }
     }
     
+    /// Decodes the instruction at PC without panicking, for diagnostics.
+    fn peek_instruction(&self) -> Option<Instruction> {
+        let opcode = OpCode::get_for(self.variant, self.bus.peek(self.pc)?)?;
+        let lo = self.bus.peek(self.pc.wrapping_add(1));
+        let hi = self.bus.peek(self.pc.wrapping_add(2));
+        let operand = match opcode.bytes {
+            1 => 0,
+            2 => lo? as u16,
+            _ => lo? as u16 | ((hi? as u16) << 8),
+        };
+        Some(Instruction { opcode, operand })
+    }
+    
     fn resolve_operand(&self, instruction: &Instruction) -> u8 {
         use opcode::Addressing::*;
         match instruction.opcode.addressing {
@@ -127,38 +433,95 @@ impl Cpu {
         
         match instruction.opcode.addressing {
             Absolute => instruction.operand,
-            AbsoluteX => instruction.operand + self.x as u16,
-            AbsoluteY => instruction.operand + self.y as u16,
+            AbsoluteX => instruction.operand.wrapping_add(self.x as u16),
+            AbsoluteY => instruction.operand.wrapping_add(self.y as u16),
             Indirect => {
-                self.bus.read_u16(instruction.operand)
+                let ptr = instruction.operand;
+                let hi_ptr = match self.variant {
+                    Variant::Cmos => ptr.wrapping_add(1),
+                    // NMOS parts don't carry into the pointer's high byte, so
+                    // JMP ($10FF) reads its high byte from $1000
+                    _ => (ptr & 0xFF00) | (ptr.wrapping_add(1) & 0x00FF),
+                };
+                self.bus.read(ptr) as u16 | ((self.bus.read(hi_ptr) as u16) << 8)
             },
             IndirectX => {
                 let op = instruction.operand as u8;
-                let ptr = op.overflowing_add(self.x).0 as u16;
-                self.bus.read_u16(ptr)
+                self.bus.read_zp_ptr(op.wrapping_add(self.x))
             },
             IndirectY => {
-                self.bus.read_u16(instruction.operand) + self.y as u16
+                self.bus.read_zp_ptr(instruction.operand as u8).wrapping_add(self.y as u16)
             },
             ZeroPage => instruction.operand,
             ZeroPageX => {
                 let op = instruction.operand as u8;
-                op.overflowing_add(self.x).0 as u16
+                op.wrapping_add(self.x) as u16
             },
             ZeroPageY => {
                 let op = instruction.operand as u8;
-                op.overflowing_add(self.y).0 as u16
+                op.wrapping_add(self.y) as u16
             },
-            Relative => {
-                let op = instruction.operand;
-                self.pc + op as u8 as i8 as i16 as u16
+            ZeroPageIndirect => self.bus.read_zp_ptr(instruction.operand as u8),
+            AbsoluteIndirectX => {
+                let ptr = instruction.operand.wrapping_add(self.x as u16);
+                self.bus.read(ptr) as u16 | ((self.bus.read(ptr.wrapping_add(1)) as u16) << 8)
             },
+            Relative => self.branch_target(instruction.operand as u8),
             m => panic!("Cannot get address with mode `{:?}`", m),
         }
     }
     
     fn branch(&mut self, instruction: &Instruction) {
-        self.pc += instruction.operand as u8 as i8 as i16 as u16;
+        self.pc = self.branch_target(instruction.operand as u8);
+    }
+    
+    /// Where a branch with `displacement` lands: the signed offset is added
+    /// to the address of the instruction after the branch, wrapping at $FFFF.
+    /// Only valid during `execute`, once `step` has moved PC past the operand.
+    fn branch_target(&self, displacement: u8) -> u16 {
+        self.pc.wrapping_add(displacement as i8 as i16 as u16)
+    }
+    
+    /// Whether the zero page bit a BBR/BBS instruction names is set.
+    fn test_zp_bit(&self, instruction: &Instruction) -> bool {
+        let value = self.bus.read_zp(instruction.operand as u8);
+        value & (1 << instruction.opcode.bit()) != 0
+    }
+    
+    /// Pushes the return address and `sr`, then jumps through `vector`.
+    /// Shared by BRK and the hardware interrupts.
+    fn interrupt(&mut self, ret: u16, sr: u8, vector: u16) {
+        self.push((ret >> 8) as u8);
+        self.push(ret as u8);
+        self.push(sr);
+        self.set_status(StatusBit::Interrupt, true);
+        if self.variant == Variant::Cmos {
+            self.set_status(StatusBit::Decimal, false);
+        }
+        self.waiting = false;
+        self.pc = self.bus.read_u16(vector);
+    }
+    
+    fn push(&mut self, value: u8) {
+        self.bus.write(STACK_BASE + self.sp as u16, value);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+    
+    fn pop(&mut self) -> u8 {
+        self.sp = self.sp.wrapping_add(1);
+        self.bus.read(STACK_BASE + self.sp as u16)
+    }
+    
+    /// Pulls SR. Break only exists in pushed copies, so it comes back clear,
+    /// and the unused bit always reads as set.
+    fn pop_status(&mut self) {
+        let value = self.pop();
+        self.sr = (value & !0x30) | 0x20;
+    }
+    
+    /// Whether ADC and SBC work in BCD. The 2A03 ignores the D flag.
+    fn decimal_mode(&self) -> bool {
+        self.status(StatusBit::Decimal) && self.variant != Variant::Rp2A03
     }
     
     fn status_u8(&self, bit: StatusBit) -> u8 {
@@ -185,25 +548,78 @@ impl Cpu {
         self.update_status(y);
     }
     
-    //-------------------------------------------------------
-    // Opcode implementation
-    
-    pub fn adc(&mut self, instruction: &Instruction) {
+    /// Binary AC + op + C, shared by ADC and SBC (which adds the inverted
+    /// operand). V is set when both inputs have the same sign and the
+    /// result's sign differs.
+    fn add_to_ac(&mut self, op: u8) {
         let ac = self.ac as u16;
-        let op = self.resolve_operand(instruction) as u16;
+        let op = op as u16;
         let sr = self.status_u8(StatusBit::Carry) as u16;
         
         let value16 = ac + op + sr;
         self.set_status(StatusBit::Carry, value16 > 0xFF);
+        self.set_status(StatusBit::Overflow, (ac ^ value16) & (op ^ value16) & 0x80 != 0);
         self.ac = value16 as u8;
         self.update_ac();
     }
     
+    /// Decimal mode ADC as on an NMOS 6502: Z comes from the binary sum, N
+    /// and V from the sum before the high digit is adjusted.
+    fn add_decimal(&mut self, op: u8) {
+        let ac = self.ac;
+        let carry = self.status(StatusBit::Carry);
+        let binary = ac.wrapping_add(op).wrapping_add(carry as u8);
+        let intermediate = bcd::add_intermediate(ac, op, carry);
+        let (value, carry) = bcd::add_u8(ac, op, carry);
+        
+        self.set_status(StatusBit::Carry, carry);
+        self.set_status(StatusBit::Zero, binary == 0);
+        self.set_status(StatusBit::Negative, intermediate & 0x80 != 0);
+        self.set_status(StatusBit::Overflow, (ac ^ intermediate) & (op ^ intermediate) & 0x80 != 0);
+        self.ac = value;
+    }
+    
+    //-------------------------------------------------------
+    // Opcode implementation
+    
+    pub fn adc(&mut self, instruction: &Instruction) {
+        let op = self.resolve_operand(instruction);
+        if self.decimal_mode() {
+            self.add_decimal(op);
+        } else {
+            self.add_to_ac(op);
+        }
+    }
+    
+    pub fn alr(&mut self, instruction: &Instruction) {
+        self.ac &= self.resolve_operand(instruction);
+        let ac_carry = (self.ac & 1) == 1;
+        self.set_status(StatusBit::Carry, ac_carry);
+        self.ac >>= 1;
+        self.update_ac();
+    }
+    
+    pub fn anc(&mut self, instruction: &Instruction) {
+        self.and(instruction);
+        let negative = self.status(StatusBit::Negative);
+        self.set_status(StatusBit::Carry, negative);
+    }
+    
     pub fn and(&mut self, instruction: &Instruction) {
         self.ac &= self.resolve_operand(instruction);
         self.update_ac();
     }
     
+    pub fn arr(&mut self, instruction: &Instruction) {
+        // AND then ROR A, except C and V come from bits 6 and 5 of the result
+        let carry = self.status_u8(StatusBit::Carry) << 7;
+        self.ac = ((self.ac & self.resolve_operand(instruction)) >> 1) | carry;
+        self.update_ac();
+        let ac = self.ac;
+        self.set_status(StatusBit::Carry, ac & 0x40 != 0);
+        self.set_status(StatusBit::Overflow, ((ac >> 6) ^ (ac >> 5)) & 1 != 0);
+    }
+    
     pub fn asl(&mut self, instruction: &Instruction) {
         use opcode::Addressing::*;
         match instruction.opcode.addressing {
@@ -224,6 +640,27 @@ impl Cpu {
         }
     }
     
+    pub fn axs(&mut self, instruction: &Instruction) {
+        // X = (A & X) - #imm, setting flags like CMP
+        let value = self.resolve_operand(instruction);
+        let ax = self.ac & self.x;
+        self.set_status(StatusBit::Carry, ax >= value);
+        self.x = ax.wrapping_sub(value);
+        self.update_x();
+    }
+    
+    pub fn bbr(&mut self, instruction: &Instruction) {
+        if !self.test_zp_bit(instruction) {
+            self.pc = self.branch_target((instruction.operand >> 8) as u8);
+        }
+    }
+    
+    pub fn bbs(&mut self, instruction: &Instruction) {
+        if self.test_zp_bit(instruction) {
+            self.pc = self.branch_target((instruction.operand >> 8) as u8);
+        }
+    }
+    
     pub fn bcc(&mut self, instruction: &Instruction) {
         if !self.status(StatusBit::Carry) {
             self.branch(instruction);
@@ -243,11 +680,15 @@ impl Cpu {
     }
     
     pub fn bit(&mut self, instruction: &Instruction) {
+        use opcode::Addressing::*;
         let value = self.resolve_operand(instruction);
         let ac = self.ac;
         self.set_status(StatusBit::Zero, ac & value == 0);
-        self.set_status(StatusBit::Overflow, value & (1 << 6) != 0);
-        self.set_status(StatusBit::Negative, value & (1 << 7) != 0);
+        // The 65C02's BIT #imm only sets Z
+        if instruction.opcode.addressing != Immediate {
+            self.set_status(StatusBit::Overflow, value & (1 << 6) != 0);
+            self.set_status(StatusBit::Negative, value & (1 << 7) != 0);
+        }
     }
     
     pub fn bmi(&mut self, instruction: &Instruction) {
@@ -268,8 +709,40 @@ impl Cpu {
         }
     }
     
-    pub fn brk(&mut self, _: &Instruction) {
-        println!("BRK: {:?}", self);
+    pub fn bra(&mut self, instruction: &Instruction) {
+        self.branch(instruction);
+    }
+    
+    pub fn brk(&mut self, instruction: &Instruction) {
+        let action = match self.brk_hook.take() {
+            Some(mut hook) => {
+                let action = hook(self, instruction.operand as u8);
+                // Keep a replacement if the hook installed one
+                if self.brk_hook.is_none() {
+                    self.brk_hook = Some(hook);
+                }
+                action
+            }
+            None => BrkAction::Dispatch,
+        };
+        if action == BrkAction::Dispatch {
+            // PC has already stepped over the signature byte
+            let pc = self.pc;
+            let sr = self.sr | 0x30;
+            self.interrupt(pc, sr, 0xFFFE);
+        }
+    }
+    
+    pub fn bvc(&mut self, instruction: &Instruction) {
+        if !self.status(StatusBit::Overflow) {
+            self.branch(instruction);
+        }
+    }
+    
+    pub fn bvs(&mut self, instruction: &Instruction) {
+        if self.status(StatusBit::Overflow) {
+            self.branch(instruction);
+        }
     }
     
     pub fn clc(&mut self, _: &Instruction) {
@@ -281,50 +754,2275 @@ impl Cpu {
     }
     
     pub fn cli(&mut self, _: &Instruction) {
-        self.set_status(StatusBit::Interrupt, true);
+        self.set_status(StatusBit::Interrupt, false);
+    }
+    
+    pub fn clv(&mut self, _: &Instruction) {
+        self.set_status(StatusBit::Overflow, false);
     }
     
     pub fn cmp(&mut self, instruction: &Instruction) {
         let value = self.resolve_operand(instruction);
         let ac = self.ac;
         self.set_status(StatusBit::Carry, ac >= value);
-        self.update_status(ac.overflowing_sub(value).0);
+        self.update_status(ac.wrapping_sub(value));
     }
     
     pub fn cpx(&mut self, instruction: &Instruction) {
         let value = self.resolve_operand(instruction);
         let x = self.x;
         self.set_status(StatusBit::Carry, x >= value);
-        self.update_status(x.overflowing_sub(value).0);
+        self.update_status(x.wrapping_sub(value));
     }
     
     pub fn cpy(&mut self, instruction: &Instruction) {
         let value = self.resolve_operand(instruction);
         let y = self.y;
         self.set_status(StatusBit::Carry, y >= value);
-        self.update_status(y.overflowing_sub(value).0);
+        self.update_status(y.wrapping_sub(value));
+    }
+    
+    pub fn dcp(&mut self, instruction: &Instruction) {
+        self.dec(instruction);
+        self.cmp(instruction);
     }
     
     pub fn dec(&mut self, instruction: &Instruction) {
-        let addr = self.resolve_address(instruction);
-        let value = self.bus.read(addr).overflowing_sub(1).0;
-        self.bus.write(addr, value);
-        self.update_status(value);
+        use opcode::Addressing::*;
+        match instruction.opcode.addressing {
+            Accumulator => {
+                self.ac = self.ac.wrapping_sub(1);
+                self.update_ac();
+            },
+            _ => {
+                let addr = self.resolve_address(instruction);
+                let value = self.bus.read(addr).wrapping_sub(1);
+                self.bus.write(addr, value);
+                self.update_status(value);
+            }
+        }
     }
     
-    pub fn dex(&mut self, instruction: &Instruction) {
-        self.x -= 1;
+    pub fn dex(&mut self, _: &Instruction) {
+        self.x = self.x.wrapping_sub(1);
         self.update_x();
     }
     
-    pub fn dey(&mut self, instruction: &Instruction) {
-        self.x -= 1;
+    pub fn dey(&mut self, _: &Instruction) {
+        self.y = self.y.wrapping_sub(1);
         self.update_y();
     }
     
+    pub fn eor(&mut self, instruction: &Instruction) {
+        self.ac ^= self.resolve_operand(instruction);
+        self.update_ac();
+    }
+    
+    pub fn inc(&mut self, instruction: &Instruction) {
+        use opcode::Addressing::*;
+        match instruction.opcode.addressing {
+            Accumulator => {
+                self.ac = self.ac.wrapping_add(1);
+                self.update_ac();
+            },
+            _ => {
+                let addr = self.resolve_address(instruction);
+                let value = self.bus.read(addr).wrapping_add(1);
+                self.bus.write(addr, value);
+                self.update_status(value);
+            }
+        }
+    }
+    
+    pub fn inx(&mut self, _: &Instruction) {
+        self.x = self.x.wrapping_add(1);
+        self.update_x();
+    }
+    
+    pub fn iny(&mut self, _: &Instruction) {
+        self.y = self.y.wrapping_add(1);
+        self.update_y();
+    }
     
+    pub fn isc(&mut self, instruction: &Instruction) {
+        self.inc(instruction);
+        self.sbc(instruction);
+    }
     
     pub fn jmp(&mut self, instruction: &Instruction) {
         self.pc = self.resolve_address(instruction);
     }
+    
+    pub fn jsr(&mut self, instruction: &Instruction) {
+        // PC already points past the operand; the 6502 pushes the address of
+        // the operand's last byte and RTS adds the one back
+        let ret = self.pc.wrapping_sub(1);
+        self.push((ret >> 8) as u8);
+        self.push(ret as u8);
+        self.pc = self.resolve_address(instruction);
+    }
+    
+    pub fn kil(&mut self, _: &Instruction) {
+        // The real chip locks up with PC still on the opcode
+        self.pc = self.pc.wrapping_sub(1);
+        self.halted = true;
+        self.jammed = true;
+    }
+    
+    pub fn lax(&mut self, instruction: &Instruction) {
+        self.lda(instruction);
+        self.tax(instruction);
+    }
+    
+    pub fn lda(&mut self, instruction: &Instruction) {
+        self.ac = self.resolve_operand(instruction);
+        self.update_ac();
+    }
+    
+    pub fn ldx(&mut self, instruction: &Instruction) {
+        self.x = self.resolve_operand(instruction);
+        self.update_x();
+    }
+    
+    pub fn ldy(&mut self, instruction: &Instruction) {
+        self.y = self.resolve_operand(instruction);
+        self.update_y();
+    }
+    
+    pub fn lsr(&mut self, instruction: &Instruction) {
+        use opcode::Addressing::*;
+        match instruction.opcode.addressing {
+            Accumulator => {
+                let ac_carry = (self.ac & 1) == 1;
+                self.set_status(StatusBit::Carry, ac_carry);
+                self.ac >>= 1;
+                self.update_ac();
+            },
+            _ => {
+                let addr = self.resolve_address(instruction);
+                let mut value = self.bus.read(addr);
+                self.set_status(StatusBit::Carry, (value & 1) == 1);
+                value >>= 1;
+                self.bus.write(addr, value);
+                self.update_status(value);
+            }
+        }
+    }
+    
+    pub fn nop(&mut self, instruction: &Instruction) {
+        use opcode::Addressing::*;
+        match instruction.opcode.addressing {
+            Implied | Immediate => {},
+            // The undocumented memory forms still perform the read
+            _ => { self.resolve_operand(instruction); },
+        }
+    }
+    
+    pub fn ora(&mut self, instruction: &Instruction) {
+        self.ac |= self.resolve_operand(instruction);
+        self.update_ac();
+    }
+    
+    pub fn pha(&mut self, _: &Instruction) {
+        let ac = self.ac;
+        self.push(ac);
+    }
+    
+    pub fn php(&mut self, _: &Instruction) {
+        // Break and the unused bit only exist in the pushed copy
+        let sr = self.sr | 0x30;
+        self.push(sr);
+    }
+    
+    pub fn phx(&mut self, _: &Instruction) {
+        let x = self.x;
+        self.push(x);
+    }
+    
+    pub fn phy(&mut self, _: &Instruction) {
+        let y = self.y;
+        self.push(y);
+    }
+    
+    pub fn pla(&mut self, _: &Instruction) {
+        self.ac = self.pop();
+        self.update_ac();
+    }
+    
+    pub fn plp(&mut self, _: &Instruction) {
+        self.pop_status();
+    }
+    
+    pub fn plx(&mut self, _: &Instruction) {
+        self.x = self.pop();
+        self.update_x();
+    }
+    
+    pub fn ply(&mut self, _: &Instruction) {
+        self.y = self.pop();
+        self.update_y();
+    }
+    
+    pub fn rla(&mut self, instruction: &Instruction) {
+        self.rol(instruction);
+        self.and(instruction);
+    }
+    
+    pub fn rmb(&mut self, instruction: &Instruction) {
+        let addr = self.resolve_address(instruction);
+        let value = self.bus.read(addr) & !(1 << instruction.opcode.bit());
+        self.bus.write(addr, value);
+    }
+    
+    pub fn rol(&mut self, instruction: &Instruction) {
+        use opcode::Addressing::*;
+        let carry = self.status_u8(StatusBit::Carry);
+        match instruction.opcode.addressing {
+            Accumulator => {
+                let ac_carry = (self.ac >> 7) == 1;
+                self.set_status(StatusBit::Carry, ac_carry);
+                self.ac = (self.ac << 1) | carry;
+                self.update_ac();
+            },
+            _ => {
+                let addr = self.resolve_address(instruction);
+                let mut value = self.bus.read(addr);
+                self.set_status(StatusBit::Carry, (value >> 7) == 1);
+                value = (value << 1) | carry;
+                self.bus.write(addr, value);
+                self.update_status(value);
+            }
+        }
+    }
+    
+    pub fn ror(&mut self, instruction: &Instruction) {
+        use opcode::Addressing::*;
+        let carry = self.status_u8(StatusBit::Carry) << 7;
+        match instruction.opcode.addressing {
+            Accumulator => {
+                let ac_carry = (self.ac & 1) == 1;
+                self.set_status(StatusBit::Carry, ac_carry);
+                self.ac = (self.ac >> 1) | carry;
+                self.update_ac();
+            },
+            _ => {
+                let addr = self.resolve_address(instruction);
+                let mut value = self.bus.read(addr);
+                self.set_status(StatusBit::Carry, (value & 1) == 1);
+                value = (value >> 1) | carry;
+                self.bus.write(addr, value);
+                self.update_status(value);
+            }
+        }
+    }
+    
+    pub fn rra(&mut self, instruction: &Instruction) {
+        self.ror(instruction);
+        self.adc(instruction);
+    }
+    
+    pub fn rti(&mut self, _: &Instruction) {
+        self.pop_status();
+        let lo = self.pop() as u16;
+        let hi = self.pop() as u16;
+        self.pc = lo | (hi << 8);
+    }
+    
+    pub fn rts(&mut self, _: &Instruction) {
+        let lo = self.pop() as u16;
+        let hi = self.pop() as u16;
+        self.pc = (lo | (hi << 8)).wrapping_add(1);
+    }
+    
+    pub fn sax(&mut self, instruction: &Instruction) {
+        let addr = self.resolve_address(instruction);
+        self.bus.write(addr, self.ac & self.x);
+    }
+    
+    pub fn sbc(&mut self, instruction: &Instruction) {
+        // A - M - (1 - C) is A + !M + C in two's complement
+        let op = self.resolve_operand(instruction);
+        if self.decimal_mode() {
+            // NMOS parts set every flag from the binary subtraction
+            let ac = self.ac;
+            let carry = self.status(StatusBit::Carry);
+            self.add_to_ac(!op);
+            self.ac = bcd::sub_u8(ac, op, carry).0;
+        } else {
+            self.add_to_ac(!op);
+        }
+    }
+    
+    pub fn sec(&mut self, _: &Instruction) {
+        self.set_status(StatusBit::Carry, true);
+    }
+    
+    pub fn sed(&mut self, _: &Instruction) {
+        self.set_status(StatusBit::Decimal, true);
+    }
+    
+    pub fn sei(&mut self, _: &Instruction) {
+        self.set_status(StatusBit::Interrupt, true);
+    }
+    
+    pub fn slo(&mut self, instruction: &Instruction) {
+        self.asl(instruction);
+        self.ora(instruction);
+    }
+    
+    pub fn smb(&mut self, instruction: &Instruction) {
+        let addr = self.resolve_address(instruction);
+        let value = self.bus.read(addr) | (1 << instruction.opcode.bit());
+        self.bus.write(addr, value);
+    }
+    
+    pub fn sre(&mut self, instruction: &Instruction) {
+        self.lsr(instruction);
+        self.eor(instruction);
+    }
+    
+    pub fn sta(&mut self, instruction: &Instruction) {
+        let addr = self.resolve_address(instruction);
+        self.bus.write(addr, self.ac);
+    }
+    
+    pub fn stp(&mut self, _: &Instruction) {
+        self.halted = true;
+    }
+    
+    pub fn stx(&mut self, instruction: &Instruction) {
+        let addr = self.resolve_address(instruction);
+        self.bus.write(addr, self.x);
+    }
+    
+    pub fn sty(&mut self, instruction: &Instruction) {
+        let addr = self.resolve_address(instruction);
+        self.bus.write(addr, self.y);
+    }
+    
+    pub fn stz(&mut self, instruction: &Instruction) {
+        let addr = self.resolve_address(instruction);
+        self.bus.write(addr, 0);
+    }
+    
+    pub fn tax(&mut self, _: &Instruction) {
+        self.x = self.ac;
+        self.update_x();
+    }
+    
+    pub fn tay(&mut self, _: &Instruction) {
+        self.y = self.ac;
+        self.update_y();
+    }
+    
+    pub fn trb(&mut self, instruction: &Instruction) {
+        let addr = self.resolve_address(instruction);
+        let value = self.bus.read(addr);
+        self.set_status(StatusBit::Zero, self.ac & value == 0);
+        self.bus.write(addr, value & !self.ac);
+    }
+    
+    pub fn tsb(&mut self, instruction: &Instruction) {
+        let addr = self.resolve_address(instruction);
+        let value = self.bus.read(addr);
+        self.set_status(StatusBit::Zero, self.ac & value == 0);
+        self.bus.write(addr, value | self.ac);
+    }
+    
+    pub fn tsx(&mut self, _: &Instruction) {
+        self.x = self.sp;
+        self.update_x();
+    }
+    
+    pub fn txa(&mut self, _: &Instruction) {
+        self.ac = self.x;
+        self.update_ac();
+    }
+    
+    pub fn txs(&mut self, _: &Instruction) {
+        // The only transfer that leaves flags alone
+        self.sp = self.x;
+    }
+    
+    pub fn tya(&mut self, _: &Instruction) {
+        self.ac = self.y;
+        self.update_ac();
+    }
+    
+    pub fn wai(&mut self, _: &Instruction) {
+        self.waiting = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bus::{Backend, BusEntry};
+    use memory::ram::Ram;
+    
+    /// Writable memory for $8000-$FFFF, so tests can set vectors and place
+    /// code at the top of the address space.
+    #[derive(Debug)]
+    struct HighRam(Vec<u8>);
+    
+    impl Backend for HighRam {
+        fn size(&self) -> u16 {
+            0x8000
+        }
+        
+        fn read(&self, addr: u16) -> u8 {
+            self.0[addr as usize]
+        }
+        
+        fn write(&mut self, addr: u16, value: u8) {
+            self.0[addr as usize] = value;
+        }
+    }
+    
+    const ORIGIN: u16 = 0x0200;
+    
+    /// A CPU with RAM over the whole address space, `program` copied to
+    /// `origin` and the reset vector pointing at it.
+    fn load_at(variant: Variant, origin: u16, program: &[u8]) -> Cpu {
+        let mut cpu = Cpu::with_variant(variant);
+        cpu.attach_backend(Ram::new_entry());
+        cpu.attach_backend(BusEntry::new(Box::new(HighRam(vec![0; 0x8000])), "HIGH".into(), 0x8000));
+        cpu.bus_mut().load(0xFFFC, &[origin as u8, (origin >> 8) as u8]);
+        cpu.bus_mut().load(origin, program);
+        cpu.reset();
+        cpu
+    }
+    
+    fn load(program: &[u8]) -> Cpu {
+        load_at(Variant::Nmos, ORIGIN, program)
+    }
+    
+    fn run(cpu: &mut Cpu, steps: usize) {
+        for _ in 0..steps {
+            cpu.step();
+        }
+    }
+    
+    #[test]
+    fn nop_sled_then_jmp() {
+        let mut program = vec![0xEA; 256];
+        program.extend(&[0x4C, 0x00, 0x04]);
+        let mut cpu = load(&program);
+        let sr = cpu.sr();
+        
+        run(&mut cpu, 256);
+        assert_eq!(cpu.pc(), ORIGIN + 256);
+        assert_eq!(cpu.sr(), sr);
+        run(&mut cpu, 1);
+        assert_eq!(cpu.pc(), 0x0400);
+        assert_eq!(cpu.sr(), sr);
+    }
+    
+    #[test]
+    fn debug_shows_registers_flags_and_next_instruction() {
+        let mut cpu = load(&[0xA2, 0x12, 0xA0, 0x34, 0x38, 0xA9, 0x80, 0x8D, 0x00, 0x03]);
+        run(&mut cpu, 4);
+        assert_eq!(
+            format!("{:?}", cpu),
+            "Cpu { pc: $0207, ac: $80, x: $12, y: $34, sp: $FF, sr: Nv-bdIzC, state: running, cycles: 8, \
+             instructions: 4, next: STA $0300, \
+             bus: [RAM $0000-$7FFF, HIGH $8000-$FFFF] }",
+        );
+    }
+    
+    #[test]
+    fn debug_with_unmapped_pc() {
+        let mut cpu = Cpu::new();
+        cpu.attach_backend(Ram::new_entry());
+        cpu.pc = 0x9000;
+        assert_eq!(
+            format!("{:?}", cpu),
+            "Cpu { pc: $9000, ac: $00, x: $00, y: $00, sp: $00, sr: nv-bdizc, state: running, cycles: 0, \
+             instructions: 0, next: ???, \
+             bus: [RAM $0000-$7FFF] }",
+        );
+    }
+    
+    /// LDA #$11, BRK #$03, LDA #$22 at $0200, with an RTI handler at $0300.
+    fn brk_program() -> Cpu {
+        let mut cpu = load(&[0xA9, 0x11, 0x00, 0x03, 0xA9, 0x22]);
+        cpu.bus_mut().load(0xFFFE, &[0x00, 0x03]);
+        cpu.bus_mut().write(0x0300, 0x40);
+        cpu.bus_mut().load(0x01FD, &[0xEE, 0xEE, 0xEE]);
+        cpu
+    }
+    
+    #[test]
+    fn brk_dispatches_through_vector_after_hook() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        
+        let mut cpu = brk_program();
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = seen.clone();
+        cpu.set_brk_hook(move |_, signature| {
+            log.borrow_mut().push(signature);
+            BrkAction::Dispatch
+        });
+        run(&mut cpu, 2);
+        assert_eq!(*seen.borrow(), [0x03]);
+        assert_eq!(cpu.pc(), 0x0300);
+        assert_eq!(cpu.sp(), 0xFC);
+        assert_eq!(cpu.bus().read(0x01FF), 0x02);
+        assert_eq!(cpu.bus().read(0x01FE), 0x04);
+        assert_eq!(cpu.bus().read(0x01FD), 0x34);
+        assert!(cpu.status(StatusBit::Interrupt));
+        
+        run(&mut cpu, 2);
+        assert_eq!(cpu.pc(), 0x0206);
+        assert_eq!(cpu.ac(), 0x22);
+        assert_eq!(cpu.sp(), 0xFF);
+    }
+    
+    #[test]
+    fn brk_handled_by_host_skips_interrupt() {
+        let mut cpu = brk_program();
+        cpu.set_status(StatusBit::Interrupt, false);
+        cpu.set_brk_hook(|cpu, signature| {
+            if signature != 3 {
+                return BrkAction::Dispatch;
+            }
+            let result = cpu.ac().wrapping_mul(2);
+            cpu.bus_mut().write(0x0010, result);
+            BrkAction::Resume
+        });
+        let sr = cpu.sr();
+        run(&mut cpu, 2);
+        assert_eq!(cpu.bus().read(0x0010), 0x22);
+        assert_eq!(cpu.pc(), 0x0204);
+        assert_eq!(cpu.sp(), 0xFF);
+        assert_eq!(cpu.sr(), sr);
+        assert_eq!(cpu.bus().read(0x01FF), 0xEE);
+        assert_eq!(cpu.bus().read(0x01FE), 0xEE);
+        assert_eq!(cpu.bus().read(0x01FD), 0xEE);
+        
+        cpu.step();
+        assert_eq!(cpu.ac(), 0x22);
+        assert_eq!(cpu.pc(), 0x0206);
+    }
+    
+    #[test]
+    fn brk_without_hook_disassembles_signature() {
+        let mut cpu = brk_program();
+        cpu.step();
+        assert!(format!("{:?}", cpu).contains("next: BRK #$03"));
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x0300);
+        assert_eq!(cpu.bus().read(0x01FE), 0x04);
+    }
+    
+    #[test]
+    fn cli_clears_interrupt_disable() {
+        // Reset leaves I set
+        let mut cpu = load(&[0x58, 0x78, 0x58]);
+        assert_eq!(cpu.sr(), 0x24);
+        cpu.step();
+        assert!(!cpu.status(StatusBit::Interrupt));
+        assert_eq!(cpu.sr(), 0x20);
+        cpu.step();
+        assert_eq!(cpu.sr(), 0x24);
+        cpu.step();
+        assert_eq!(cpu.sr(), 0x20);
+    }
+    
+    /// The N and Z flags, in that order.
+    fn nz(cpu: &Cpu) -> (bool, bool) {
+        (cpu.status(StatusBit::Negative), cpu.status(StatusBit::Zero))
+    }
+    
+    #[test]
+    fn loads_and_stores_set_flags_from_value() {
+        let mut cpu = load(&[
+            0xA9, 0x00,       // LDA #$00
+            0x85, 0x10,       // STA $10
+            0xA2, 0x80,       // LDX #$80
+            0x8E, 0x00, 0x03, // STX $0300
+            0xA4, 0x10,       // LDY $10
+            0xAD, 0x00, 0x03, // LDA $0300
+            0x8C, 0x01, 0x03, // STY $0301
+            0xAE, 0x10, 0x00, // LDX $0010
+        ]);
+        cpu.step();
+        assert_eq!((cpu.ac(), nz(&cpu)), (0x00, (false, true)));
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x0010), 0x00);
+        cpu.step();
+        assert_eq!((cpu.x(), nz(&cpu)), (0x80, (true, false)));
+        // Stores leave the flags alone
+        cpu.step();
+        assert_eq!((cpu.bus().read(0x0300), nz(&cpu)), (0x80, (true, false)));
+        cpu.step();
+        assert_eq!((cpu.y(), nz(&cpu)), (0x00, (false, true)));
+        cpu.step();
+        assert_eq!((cpu.ac(), nz(&cpu)), (0x80, (true, false)));
+        cpu.step();
+        assert_eq!((cpu.bus().read(0x0301), nz(&cpu)), (0x00, (true, false)));
+        cpu.step();
+        assert_eq!((cpu.x(), nz(&cpu)), (0x00, (false, true)));
+    }
+    
+    #[test]
+    fn stack_pointer_wraps() {
+        let mut cpu = load(&[
+            0xA2, 0x00, // LDX #$00
+            0x9A,       // TXS
+            0xA9, 0x42, // LDA #$42
+            0x48,       // PHA
+            0x48,       // PHA
+            0x68,       // PLA
+            0x68,       // PLA
+        ]);
+        run(&mut cpu, 4);
+        assert_eq!(cpu.bus().read(0x0100), 0x42);
+        assert_eq!(cpu.sp(), 0xFF);
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x01FF), 0x42);
+        assert_eq!(cpu.sp(), 0xFE);
+        run(&mut cpu, 2);
+        assert_eq!(cpu.sp(), 0x00);
+        assert_eq!(cpu.ac(), 0x42);
+    }
+    
+    #[test]
+    fn txs_sets_up_stack_for_pha() {
+        let mut cpu = load(&[
+            0xA2, 0xFF, // LDX #$FF
+            0x9A,       // TXS
+            0xA9, 0x5A, // LDA #$5A
+            0x48,       // PHA
+        ]);
+        cpu.sp = 0x10;
+        cpu.step();
+        let sr = cpu.sr();
+        cpu.step();
+        assert_eq!((cpu.sp(), cpu.sr()), (0xFF, sr));
+        run(&mut cpu, 2);
+        assert_eq!(cpu.bus().read(0x01FF), 0x5A);
+        assert_eq!(cpu.sp(), 0xFE);
+    }
+    
+    #[test]
+    fn php_plp_round_trip() {
+        let mut cpu = load(&[
+            0x38, // SEC
+            0xF8, // SED
+            0x08, // PHP
+            0x18, // CLC
+            0xD8, // CLD
+            0x58, // CLI
+            0x28, // PLP
+        ]);
+        run(&mut cpu, 3);
+        assert_eq!(cpu.sp(), 0xFE);
+        assert_eq!(cpu.bus().read(0x01FF), 0x3D);
+        run(&mut cpu, 3);
+        assert_eq!(cpu.sr(), 0x20);
+        cpu.step();
+        assert_eq!(cpu.sr(), 0x2D);
+        assert_eq!(cpu.sp(), 0xFF);
+    }
+    
+    #[test]
+    fn dey_decrements_y_only() {
+        let mut cpu = load(&[0xA2, 0x55, 0xA0, 0x02, 0x88, 0x88, 0x88]);
+        run(&mut cpu, 3);
+        assert_eq!((cpu.y(), nz(&cpu)), (0x01, (false, false)));
+        cpu.step();
+        assert_eq!((cpu.y(), nz(&cpu)), (0x00, (false, true)));
+        cpu.step();
+        assert_eq!((cpu.y(), nz(&cpu)), (0xFF, (true, false)));
+        assert_eq!(cpu.x(), 0x55);
+    }
+    
+    /// Runs CLC/SEC, LDA #a, ADC #b and returns A with the N, V, Z and C flags.
+    fn adc(a: u8, b: u8, carry: bool) -> (u8, bool, bool, bool, bool) {
+        let mut cpu = load(&[if carry { 0x38 } else { 0x18 }, 0xA9, a, 0x69, b]);
+        run(&mut cpu, 3);
+        let flag = |bit| cpu.status(bit);
+        use self::StatusBit::*;
+        (cpu.ac(), flag(Negative), flag(Overflow), flag(Zero), flag(Carry))
+    }
+    
+    #[test]
+    fn adc_sets_overflow_on_signed_overflow() {
+        // A, N, V, Z, C
+        // Two positives making a negative, and two negatives making a positive
+        assert_eq!(adc(0x50, 0x50, false), (0xA0, true, true, false, false));
+        assert_eq!(adc(0xD0, 0x90, false), (0x60, false, true, false, true));
+        // Same signs that still fit
+        assert_eq!(adc(0x50, 0x10, false), (0x60, false, false, false, false));
+        // Mixed signs can't overflow, whatever the carry does
+        assert_eq!(adc(0x50, 0x90, false), (0xE0, true, false, false, false));
+        assert_eq!(adc(0x50, 0xD0, false), (0x20, false, false, false, true));
+        assert_eq!(adc(0x01, 0xFF, false), (0x00, false, false, true, true));
+        // The carry in can tip the result over
+        assert_eq!(adc(0x7F, 0x00, true), (0x80, true, true, false, false));
+        
+        // V is cleared again by an add that doesn't overflow
+        let mut cpu = load(&[0xA9, 0x50, 0x69, 0x50, 0x69, 0x10]);
+        run(&mut cpu, 2);
+        assert!(cpu.status(StatusBit::Overflow));
+        cpu.step();
+        assert!(!cpu.status(StatusBit::Overflow));
+    }
+    
+    #[test]
+    fn jsr_rts_nested_three_deep() {
+        let mut cpu = load(&[0x20, 0x00, 0x03, 0xA0, 0x01]);
+        cpu.bus_mut().load(0x0300, &[0x20, 0x00, 0x04, 0x60]);
+        cpu.bus_mut().load(0x0400, &[0x20, 0x00, 0x05, 0x60]);
+        cpu.bus_mut().load(0x0500, &[0xA9, 0x33, 0x60]);
+        run(&mut cpu, 3);
+        assert_eq!(cpu.pc(), 0x0500);
+        assert_eq!(cpu.sp(), 0xF9);
+        let stack: Vec<u8> = (0x01FA..=0x01FF).map(|addr| cpu.bus().read(addr)).collect();
+        // Each JSR pushes the address of its own last byte, high byte first
+        assert_eq!(stack, [0x02, 0x04, 0x02, 0x03, 0x02, 0x02]);
+        
+        run(&mut cpu, 2);
+        assert_eq!((cpu.pc(), cpu.sp()), (0x0403, 0xFB));
+        cpu.step();
+        assert_eq!((cpu.pc(), cpu.sp()), (0x0303, 0xFD));
+        cpu.step();
+        assert_eq!((cpu.pc(), cpu.sp()), (0x0203, 0xFF));
+        cpu.step();
+        assert_eq!((cpu.ac(), cpu.y()), (0x33, 0x01));
+    }
+    
+    #[test]
+    fn brk_pushes_return_and_status() {
+        let mut cpu = load(&[0x00, 0x00]);
+        cpu.bus_mut().load(0xFFFE, &[0x00, 0x04]);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x0400);
+        assert_eq!(cpu.sp(), 0xFC);
+        assert_eq!(cpu.bus().read(0x01FF), 0x02);
+        assert_eq!(cpu.bus().read(0x01FE), 0x02);
+        assert_eq!(cpu.bus().read(0x01FD), 0x34);
+        assert!(cpu.status(StatusBit::Interrupt));
+        assert!(!cpu.status(StatusBit::Break));
+    }
+    
+    #[test]
+    fn brk_handler_returns_with_rti() {
+        let mut cpu = load(&[0x00, 0x00, 0xA2, 0x09]);
+        cpu.bus_mut().load(0xFFFE, &[0x00, 0x04]);
+        cpu.bus_mut().load(0x0400, &[0xA9, 0x77, 0x40]);
+        cpu.set_status(StatusBit::Interrupt, false);
+        run(&mut cpu, 2);
+        assert_eq!((cpu.pc(), cpu.ac()), (0x0402, 0x77));
+        assert_eq!(cpu.bus().read(0x01FD), 0x30);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x0202);
+        assert_eq!(cpu.sp(), 0xFF);
+        // I comes back clear and the stacked B doesn't become live
+        assert_eq!(cpu.sr(), 0x20);
+        cpu.step();
+        assert_eq!(cpu.x(), 0x09);
+    }
+    
+    #[test]
+    fn dex_wraps_below_zero() {
+        let mut cpu = load(&[0xA2, 0x01, 0xCA, 0xCA]);
+        run(&mut cpu, 2);
+        assert_eq!((cpu.x(), nz(&cpu)), (0x00, (false, true)));
+        cpu.step();
+        assert_eq!((cpu.x(), nz(&cpu)), (0xFF, (true, false)));
+    }
+    
+    #[test]
+    fn shifts_and_rotates_move_carry() {
+        let carry = |cpu: &Cpu| cpu.status(StatusBit::Carry);
+        let mut cpu = load(&[
+            0xA9, 0x81, // LDA #$81
+            0x4A,       // LSR A
+            0x2A,       // ROL A
+            0x2A,       // ROL A
+            0x38,       // SEC
+            0x6A,       // ROR A
+            0x6A,       // ROR A
+        ]);
+        run(&mut cpu, 2);
+        assert_eq!((cpu.ac(), carry(&cpu)), (0x40, true));
+        cpu.step();
+        assert_eq!((cpu.ac(), carry(&cpu)), (0x81, false));
+        cpu.step();
+        assert_eq!((cpu.ac(), carry(&cpu)), (0x02, true));
+        run(&mut cpu, 2);
+        assert_eq!((cpu.ac(), carry(&cpu), nz(&cpu)), (0x81, false, (true, false)));
+        cpu.step();
+        assert_eq!((cpu.ac(), carry(&cpu), nz(&cpu)), (0x40, true, (false, false)));
+    }
+    
+    #[test]
+    fn shifts_and_rotates_on_memory() {
+        let mut cpu = load(&[
+            0x46, 0x10,       // LSR $10
+            0x26, 0x11,       // ROL $11
+            0xA2, 0x01,       // LDX #$01
+            0x7E, 0xFF, 0x02, // ROR $02FF,X
+            0x56, 0x0F,       // LSR $0F,X
+        ]);
+        cpu.bus_mut().load(0x0010, &[0x01, 0x80]);
+        cpu.bus_mut().write(0x0300, 0x02);
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x0010), 0x00);
+        assert!(cpu.status(StatusBit::Carry) && cpu.status(StatusBit::Zero));
+        // ROL takes the carry from LSR and pushes out bit 7
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x0011), 0x01);
+        assert!(cpu.status(StatusBit::Carry));
+        run(&mut cpu, 2);
+        assert_eq!(cpu.bus().read(0x0300), 0x81);
+        assert_eq!(nz(&cpu), (true, false));
+        assert!(!cpu.status(StatusBit::Carry));
+        assert_eq!(cpu.ac(), 0x00);
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x0010), 0x00);
+        assert_eq!(nz(&cpu), (false, true));
+    }
+    
+    #[test]
+    fn inx_loop_until_wrap() {
+        let mut cpu = load(&[
+            0xA2, 0xFB, // LDX #$FB
+            0xC8,       // loop: INY
+            0xE8,       // INX
+            0xD0, 0xFC, // BNE loop
+            0xEA,       // NOP
+        ]);
+        cpu.step();
+        let mut steps = 0;
+        while cpu.pc() != 0x0206 {
+            cpu.step();
+            steps += 1;
+        }
+        assert_eq!(steps, 15);
+        assert_eq!((cpu.x(), cpu.y()), (0x00, 0x05));
+        assert_eq!(nz(&cpu), (false, true));
+    }
+    
+    #[test]
+    fn reset_sets_stack_pointer_for_jsr() {
+        let mut cpu = load(&[0x20, 0x34, 0x12]);
+        cpu.sp = 0x00;
+        cpu.reset();
+        assert_eq!(cpu.sp(), 0xFF);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x1234);
+        assert_eq!(cpu.sp(), 0xFD);
+        assert_eq!(cpu.bus().read(0x01FF), 0x02);
+        assert_eq!(cpu.bus().read(0x01FE), 0x02);
+    }
+    
+    /// Where `group_one` puts the operand of each non-immediate mode.
+    const OPERAND: u16 = 0x0344;
+    
+    /// A CPU about to run the group one instruction `base` (ORA $01, EOR $41,
+    /// STA $81, ...) in addressing mode `mode`, the opcode's bits 2-4. Every
+    /// mode reaches `OPERAND`, which holds `value`, except the zero page
+    /// modes, which reach $44, and immediate, which uses `value` directly.
+    /// X is $04 and Y is $08. Returns the effective address too.
+    fn group_one(base: u8, mode: u8, value: u8) -> (Cpu, Option<u16>) {
+        let (operand, target): (&[u8], _) = match mode {
+            0 => (&[0x20], Some(OPERAND)),             // ($20,X), pointer at $24
+            1 => (&[0x44], Some(0x0044)),              // $44
+            2 => (&[value], None),                     // #value
+            3 => (&[0x44, 0x03], Some(OPERAND)),       // $0344
+            4 => (&[0x30], Some(OPERAND)),             // ($30),Y
+            5 => (&[0x40], Some(0x0044)),              // $40,X
+            6 => (&[0x3C, 0x03], Some(OPERAND)),       // $033C,Y
+            7 => (&[0x40, 0x03], Some(OPERAND)),       // $0340,X
+            _ => unreachable!(),
+        };
+        let mut program = vec![0xA2, 0x04, 0xA0, 0x08, base | (mode << 2)];
+        program.extend_from_slice(operand);
+        let mut cpu = load(&program);
+        cpu.bus_mut().write_u16(0x0024, OPERAND);
+        cpu.bus_mut().write_u16(0x0030, OPERAND - 8);
+        if let Some(addr) = target {
+            cpu.bus_mut().write(addr, value);
+        }
+        run(&mut cpu, 2);
+        (cpu, target)
+    }
+    
+    #[test]
+    fn logic_ops_in_every_mode() {
+        // (base opcode, AC, operand, result)
+        let cases = [
+            (0x41, 0xF0, 0x3C, 0xCC), // EOR
+            (0x41, 0x5A, 0x5A, 0x00),
+            (0x41, 0x0F, 0x00, 0x0F),
+            (0x01, 0x5A, 0x00, 0x5A), // ORA
+            (0x01, 0x5A, 0xFF, 0xFF),
+            (0x01, 0x00, 0x00, 0x00),
+            (0x01, 0x01, 0x80, 0x81),
+        ];
+        for mode in 0..8 {
+            for &(base, ac, value, result) in &cases {
+                // Only N and Z may change, whatever else is set
+                for &sr in &[0x20, 0xEF] {
+                    let (mut cpu, _) = group_one(base, mode, value);
+                    cpu.ac = ac;
+                    cpu.sr = sr;
+                    cpu.step();
+                    let code = base | (mode << 2);
+                    assert_eq!(cpu.ac(), result, "${:02X} on ${:02X}", code, ac);
+                    assert_eq!(cpu.sr(), with_nz(sr, result), "${:02X} on ${:02X}", code, ac);
+                    let bytes = OpCode::get(code).unwrap().bytes;
+                    assert_eq!(cpu.pc(), ORIGIN + 4 + bytes as u16, "${:02X}", code);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn inc_memory_wraps_and_leaves_carry() {
+        let mut cpu = load(&[
+            0x38,             // SEC
+            0xE6, 0x10,       // INC $10
+            0xE6, 0x10,       // INC $10
+            0x18,             // CLC
+            0xEE, 0x00, 0x03, // INC $0300
+            0xA2, 0x01,       // LDX #$01
+            0xFE, 0xFF, 0x02, // INC $02FF,X
+        ]);
+        cpu.bus_mut().write(0x0010, 0xFE);
+        cpu.bus_mut().write(0x0300, 0x41);
+        run(&mut cpu, 2);
+        assert_eq!((cpu.bus().read(0x0010), nz(&cpu)), (0xFF, (true, false)));
+        assert!(cpu.status(StatusBit::Carry));
+        cpu.step();
+        assert_eq!((cpu.bus().read(0x0010), nz(&cpu)), (0x00, (false, true)));
+        assert!(cpu.status(StatusBit::Carry));
+        run(&mut cpu, 2);
+        assert_eq!((cpu.bus().read(0x0300), nz(&cpu)), (0x42, (false, false)));
+        assert!(!cpu.status(StatusBit::Carry));
+        run(&mut cpu, 2);
+        assert_eq!(cpu.bus().read(0x0300), 0x43);
+        assert!(!cpu.status(StatusBit::Carry));
+    }
+    
+    #[test]
+    fn inx_and_iny_boundaries() {
+        let mut cpu = load(&[
+            0xA2, 0x7F, // LDX #$7F
+            0xE8,       // INX
+            0xA0, 0xFF, // LDY #$FF
+            0xC8,       // INY
+            0xC8,       // INY
+        ]);
+        run(&mut cpu, 2);
+        assert_eq!((cpu.x(), nz(&cpu)), (0x80, (true, false)));
+        run(&mut cpu, 2);
+        assert_eq!((cpu.y(), nz(&cpu)), (0x00, (false, true)));
+        cpu.step();
+        assert_eq!((cpu.y(), nz(&cpu)), (0x01, (false, false)));
+        assert_eq!(cpu.x(), 0x80);
+    }
+    
+    #[test]
+    fn jsr_pushes_return_address() {
+        let mut cpu = load(&[0x20, 0x34, 0x12]);
+        cpu.sp = 0x80;
+        let sr = cpu.sr();
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x1234);
+        assert_eq!(cpu.sp(), 0x7E);
+        // The address of the JSR's last byte, high byte first
+        assert_eq!(cpu.bus().read(0x0180), 0x02);
+        assert_eq!(cpu.bus().read(0x017F), 0x02);
+        assert_eq!(cpu.sr(), sr);
+    }
+    
+    #[test]
+    fn rts_returns_after_jsr() {
+        let mut cpu = load(&[0x20, 0x34, 0x12, 0xE8]);
+        cpu.bus_mut().write(0x1234, 0x60);
+        run(&mut cpu, 2);
+        assert_eq!((cpu.pc(), cpu.sp()), (0x0203, 0xFF));
+        cpu.step();
+        assert_eq!(cpu.x(), 0x01);
+    }
+    
+    #[test]
+    fn rts_wraps_past_top_of_memory() {
+        let mut cpu = load(&[0x60]);
+        cpu.bus_mut().load(0x01FE, &[0xFF, 0xFF]);
+        cpu.sp = 0xFD;
+        cpu.step();
+        assert_eq!((cpu.pc(), cpu.sp()), (0x0000, 0xFF));
+        
+        let mut cpu = load(&[0x60]);
+        cpu.bus_mut().load(0x01FE, &[0xFE, 0xFF]);
+        cpu.sp = 0xFD;
+        cpu.step();
+        assert_eq!(cpu.pc(), 0xFFFF);
+    }
+    
+    /// Decimal ADC on an NMOS 6502, after appendix A of Bruce Clark's
+    /// "Decimal Mode" tutorial on 6502.org. Returns A, N, V, Z and C. Z comes
+    /// from the binary sum; N and V from the sum before the high digit is
+    /// adjusted.
+    fn reference_decimal_adc(a: u8, b: u8, c: bool) -> (u8, bool, bool, bool, bool) {
+        let (a, b, c) = (a as i32, b as i32, c as i32);
+        let mut al = (a & 0x0F) + (b & 0x0F) + c;
+        if al >= 0x0A {
+            al = ((al + 0x06) & 0x0F) + 0x10;
+        }
+        let mut sum = (a & 0xF0) + (b & 0xF0) + al;
+        let signed = (a as u8 as i8 as i32 & !0x0F) + (b as u8 as i8 as i32 & !0x0F) + al;
+        if sum >= 0xA0 {
+            sum += 0x60;
+        }
+        let binary = (a + b + c) & 0xFF;
+        (sum as u8, signed & 0x80 != 0, !(-128..=127).contains(&signed), binary == 0, sum >= 0x100)
+    }
+    
+    /// Decimal SBC on an NMOS 6502, after the same tutorial. The flags are
+    /// the binary ones.
+    fn reference_decimal_sbc(a: u8, b: u8, c: bool) -> (u8, bool, bool, bool, bool) {
+        let (a, b, c) = (a as i32, b as i32, c as i32);
+        let mut al = (a & 0x0F) - (b & 0x0F) + c - 1;
+        if al < 0 {
+            al = ((al - 0x06) & 0x0F) - 0x10;
+        }
+        let mut result = (a & 0xF0) - (b & 0xF0) + al;
+        if result < 0 {
+            result -= 0x60;
+        }
+        let binary = a - b + c - 1;
+        let signed = a as u8 as i8 as i32 - b as u8 as i8 as i32 + c - 1;
+        (result as u8, binary & 0x80 != 0, !(-128..=127).contains(&signed), binary & 0xFF == 0, binary >= 0)
+    }
+    
+    /// Runs ADC or SBC #b once with A = a and D set.
+    fn decimal(cpu: &mut Cpu, opcode: u8, a: u8, b: u8, c: bool) -> (u8, bool, bool, bool, bool) {
+        use self::StatusBit::*;
+        cpu.bus_mut().load(ORIGIN, &[opcode, b]);
+        cpu.pc = ORIGIN;
+        cpu.ac = a;
+        cpu.sr = 0x28;
+        cpu.set_status(Carry, c);
+        cpu.step();
+        let flag = |bit| cpu.status(bit);
+        (cpu.ac(), flag(Negative), flag(Overflow), flag(Zero), flag(Carry))
+    }
+    
+    #[test]
+    fn decimal_mode_matches_reference_for_all_inputs() {
+        let mut cpu = load(&[]);
+        for a in 0..=0xFFu8 {
+            for b in 0..=0xFFu8 {
+                for &c in &[false, true] {
+                    assert_eq!(decimal(&mut cpu, 0x69, a, b, c), reference_decimal_adc(a, b, c), "${:02X}+${:02X}+{}", a, b, c as u8);
+                    assert_eq!(decimal(&mut cpu, 0xE9, a, b, c), reference_decimal_sbc(a, b, c), "${:02X}-${:02X}-{}", a, b, !c as u8);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn decimal_mode_carries_and_borrows() {
+        let mut cpu = load(&[]);
+        // A, N, V, Z, C
+        assert_eq!(decimal(&mut cpu, 0x69, 0x09, 0x01, false), (0x10, false, false, false, false));
+        assert_eq!(decimal(&mut cpu, 0x69, 0x99, 0x01, false), (0x00, true, false, false, true));
+        // N and V come from $A5, the sum before the high digit is adjusted
+        assert_eq!(decimal(&mut cpu, 0x69, 0x58, 0x46, true), (0x05, true, true, false, true));
+        // Borrow from the tens digit, and out of the byte
+        assert_eq!(decimal(&mut cpu, 0xE9, 0x10, 0x01, true), (0x09, false, false, false, true));
+        assert_eq!(decimal(&mut cpu, 0xE9, 0x40, 0x13, false), (0x26, false, false, false, true));
+        assert_eq!(decimal(&mut cpu, 0xE9, 0x00, 0x01, true), (0x99, true, false, false, false));
+    }
+    
+    /// SR as it should be after an instruction that sets N and Z from
+    /// `value` and touches nothing else.
+    fn with_nz(sr: u8, value: u8) -> u8 {
+        (sr & !0x82) | (value & 0x80) | if value == 0 { 0x02 } else { 0x00 }
+    }
+    
+    #[test]
+    fn lda_in_every_mode() {
+        for mode in 0..8 {
+            for &value in &[0x00, 0x01, 0x7F, 0x80, 0xFF] {
+                let (mut cpu, _) = group_one(0xA1, mode, value);
+                // Everything else set, so only N and Z can change
+                cpu.sr = 0xEF;
+                cpu.step();
+                assert_eq!(cpu.ac(), value, "mode {}", mode);
+                assert_eq!(cpu.sr(), with_nz(0xEF, value), "mode {} ${:02X}", mode, value);
+                assert_eq!((cpu.x(), cpu.y()), (0x04, 0x08));
+            }
+        }
+    }
+    
+    #[test]
+    fn ldx_and_ldy_in_every_mode() {
+        let cases: &[(&[u8], Option<u16>)] = &[
+            (&[0xA2, 0xAA], None),                  // LDX #
+            (&[0xA6, 0x44], Some(0x0044)),          // LDX $44
+            (&[0xB6, 0x3C], Some(0x0044)),          // LDX $3C,Y
+            (&[0xAE, 0x44, 0x03], Some(OPERAND)),   // LDX $0344
+            (&[0xBE, 0x3C, 0x03], Some(OPERAND)),   // LDX $033C,Y
+            (&[0xA0, 0xAA], None),                  // LDY #
+            (&[0xA4, 0x44], Some(0x0044)),          // LDY $44
+            (&[0xB4, 0x40], Some(0x0044)),          // LDY $40,X
+            (&[0xAC, 0x44, 0x03], Some(OPERAND)),   // LDY $0344
+            (&[0xBC, 0x40, 0x03], Some(OPERAND)),   // LDY $0340,X
+        ];
+        for &(program, target) in cases {
+            for &value in &[0x00, 0x01, 0x7F, 0x80, 0xFF] {
+                let mut program = program.to_vec();
+                if target.is_none() {
+                    program[1] = value;
+                }
+                let mut cpu = load(&program);
+                if let Some(addr) = target {
+                    cpu.bus_mut().write(addr, value);
+                }
+                cpu.ac = 0x5A;
+                cpu.x = 0x04;
+                cpu.y = 0x08;
+                cpu.sr = 0xEF;
+                cpu.step();
+                let loads_x = program[0] & 0x03 == 0x02;
+                let (loaded, other) = if loads_x { (cpu.x(), cpu.y()) } else { (cpu.y(), cpu.x()) };
+                assert_eq!(loaded, value, "{:02X?}", program);
+                assert_eq!(other, if loads_x { 0x08 } else { 0x04 });
+                assert_eq!(cpu.ac(), 0x5A);
+                assert_eq!(cpu.sr(), with_nz(0xEF, value), "{:02X?}", program);
+                assert_eq!(cpu.pc(), ORIGIN + program.len() as u16);
+            }
+        }
+    }
+    
+    #[test]
+    fn lsr_shifts_into_carry() {
+        let carry = |cpu: &Cpu| cpu.status(StatusBit::Carry);
+        let mut cpu = load(&[0xA9, 0x01, 0x4A, 0xA9, 0x80, 0x4A]);
+        run(&mut cpu, 2);
+        assert_eq!((cpu.ac(), carry(&cpu), nz(&cpu)), (0x00, true, (false, true)));
+        run(&mut cpu, 2);
+        assert_eq!((cpu.ac(), carry(&cpu), nz(&cpu)), (0x40, false, (false, false)));
+        
+        // Eight shifts walk every bit out through C, and N is never set
+        let mut cpu = load(&[0xA9, 0xA5, 0x4A, 0x4A, 0x4A, 0x4A, 0x4A, 0x4A, 0x4A, 0x4A]);
+        cpu.step();
+        let mut out = 0;
+        for i in 0..8 {
+            cpu.step();
+            out |= (carry(&cpu) as u8) << i;
+            assert!(!cpu.status(StatusBit::Negative));
+        }
+        assert_eq!(out, 0xA5);
+        assert_eq!((cpu.ac(), nz(&cpu)), (0x00, (false, true)));
+    }
+    
+    #[test]
+    fn lsr_memory() {
+        let mut cpu = load(&[0x4E, 0x00, 0x03, 0x46, 0x10]);
+        cpu.bus_mut().write(0x0300, 0x03);
+        cpu.bus_mut().write(0x0010, 0x80);
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x0300), 0x01);
+        assert!(cpu.status(StatusBit::Carry));
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x0010), 0x40);
+        assert!(!cpu.status(StatusBit::Carry));
+        assert_eq!(cpu.ac(), 0x00);
+    }
+    
+    #[test]
+    fn nop_variants_change_nothing_but_pc() {
+        use opcode::OpId;
+        let nops: Vec<OpCode> = (0..=0xFFu8)
+            .filter_map(OpCode::get)
+            .filter(|op| op.id == OpId::NOP)
+            .collect();
+        assert_eq!(nops.len(), 28);
+        for op in nops {
+            let mut cpu = load(&[op.code, 0x10, 0x03]);
+            cpu.bus_mut().write(0x0010, 0x55);
+            cpu.bus_mut().write(0x0314, 0x66);
+            cpu.ac = 0x12;
+            cpu.x = 0x04;
+            cpu.y = 0x56;
+            cpu.sr = 0xE3;
+            cpu.step();
+            assert_eq!(cpu.pc(), ORIGIN + op.bytes as u16, "${:02X}", op.code);
+            assert_eq!((cpu.ac(), cpu.x(), cpu.y(), cpu.sp(), cpu.sr()), (0x12, 0x04, 0x56, 0xFF, 0xE3), "${:02X}", op.code);
+            assert_eq!((cpu.bus().read(0x0010), cpu.bus().read(0x0014), cpu.bus().read(0x0314)), (0x55, 0x00, 0x66));
+        }
+    }
+    
+    #[test]
+    fn undocumented_read_modify_write() {
+        // (program, memory at $10, A, X, C in) => (memory, A, X, N, V, Z, C)
+        type Case = (&'static [u8], u8, u8, u8, bool);
+        type Outcome = (u8, u8, u8, bool, bool, bool, bool);
+        let cases: &[(Case, Outcome)] = &[
+            ((&[0xA7, 0x10], 0x80, 0x00, 0x00, false), (0x80, 0x80, 0x80, true, false, false, false)),       // LAX $10
+            ((&[0xAF, 0x10, 0x00], 0x00, 0x55, 0x55, true), (0x00, 0x00, 0x00, false, false, true, true)),  // LAX $0010
+            ((&[0x87, 0x10], 0x99, 0xF0, 0x3C, true), (0x30, 0xF0, 0x3C, false, false, false, true)),        // SAX $10
+            ((&[0xC7, 0x10], 0x41, 0x40, 0x00, false), (0x40, 0x40, 0x00, false, false, true, true)),        // DCP $10
+            ((&[0xC7, 0x10], 0x00, 0x40, 0x00, true), (0xFF, 0x40, 0x00, false, false, false, false)),       // DCP $10
+            ((&[0xE7, 0x10], 0x3F, 0x50, 0x00, true), (0x40, 0x10, 0x00, false, false, false, true)),        // ISC $10
+            ((&[0xE7, 0x10], 0x7F, 0x00, 0x00, true), (0x80, 0x80, 0x00, true, true, false, false)),         // ISC $10
+            ((&[0x07, 0x10], 0x81, 0x04, 0x00, false), (0x02, 0x06, 0x00, false, false, false, true)),       // SLO $10
+            ((&[0x27, 0x10], 0x81, 0xFF, 0x00, true), (0x03, 0x03, 0x00, false, false, false, true)),        // RLA $10
+            ((&[0x27, 0x10], 0x40, 0x7F, 0x00, false), (0x80, 0x00, 0x00, false, false, true, false)),       // RLA $10
+            ((&[0x47, 0x10], 0x03, 0xFF, 0x00, false), (0x01, 0xFE, 0x00, true, false, false, true)),        // SRE $10
+            ((&[0x67, 0x10], 0x02, 0x10, 0x00, true), (0x81, 0x91, 0x00, true, false, false, false)),        // RRA $10
+            ((&[0x67, 0x10], 0x03, 0x10, 0x00, false), (0x01, 0x12, 0x00, false, false, false, false)),      // RRA $10
+            ((&[0x67, 0x10], 0x00, 0x7F, 0x00, true), (0x80, 0xFF, 0x00, true, false, false, false)),        // RRA $10
+        ];
+        for &((program, memory, ac, x, carry), expected) in cases {
+            use self::StatusBit::*;
+            let mut cpu = load(program);
+            cpu.bus_mut().write(0x0010, memory);
+            cpu.ac = ac;
+            cpu.x = x;
+            cpu.set_status(Carry, carry);
+            cpu.step();
+            let flag = |bit| cpu.status(bit);
+            let outcome = (cpu.bus().read(0x0010), cpu.ac(), cpu.x(), flag(Negative), flag(Overflow), flag(Zero), flag(Carry));
+            assert_eq!(outcome, expected, "{:02X?} on ${:02X}", program, memory);
+        }
+    }
+    
+    #[test]
+    fn undocumented_indexed_and_indirect_forms() {
+        let mut cpu = load(&[
+            0xA0, 0x04,       // LDY #$04
+            0xB3, 0x20,       // LAX ($20),Y
+            0xA9, 0x0F,       // LDA #$0F
+            0x83, 0x1C,       // SAX ($1C,X)
+            0xDB, 0x00, 0x03, // DCP $0300,Y
+            0x1F, 0xF0, 0x02, // SLO $02F0,X
+        ]);
+        cpu.bus_mut().write_u16(0x0020, 0x0300);
+        cpu.bus_mut().write(0x0304, 0xA4);
+        cpu.bus_mut().write_u16(0x00C0, 0x0400);
+        cpu.bus_mut().write(0x0394, 0x21);
+        run(&mut cpu, 2);
+        assert_eq!((cpu.ac(), cpu.x(), nz(&cpu)), (0xA4, 0xA4, (true, false)));
+        // ($1C,X) with X = $A4 wraps to the pointer at $C0
+        run(&mut cpu, 2);
+        assert_eq!(cpu.bus().read(0x0400), 0x04);
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x0304), 0xA3);
+        assert!(!cpu.status(StatusBit::Carry));
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x0394), 0x42);
+        assert_eq!(cpu.ac(), 0x4F);
+    }
+    
+    #[test]
+    fn undocumented_immediate_opcodes() {
+        // (opcode, operand, A, X, C in) => (A, X, N, V, Z, C)
+        type Case = (u8, u8, u8, u8, bool);
+        type Outcome = (u8, u8, bool, bool, bool, bool);
+        let cases: &[(Case, Outcome)] = &[
+            // ANC copies N into C
+            ((0x0B, 0x80, 0xFF, 0x00, false), (0x80, 0x00, true, false, false, true)),
+            ((0x2B, 0x7F, 0xFF, 0x00, true), (0x7F, 0x00, false, false, false, false)),
+            // ALR is AND then LSR A
+            ((0x4B, 0x03, 0xFF, 0x00, false), (0x01, 0x00, false, false, false, true)),
+            ((0x4B, 0xFE, 0x01, 0x00, true), (0x00, 0x00, false, false, true, false)),
+            // ARR is AND then ROR A, with C from bit 6 and V from bit 6 ^ bit 5
+            ((0x6B, 0xC0, 0xFF, 0x00, true), (0xE0, 0x00, true, false, false, true)),
+            ((0x6B, 0x40, 0xFF, 0x00, false), (0x20, 0x00, false, true, false, false)),
+            ((0x6B, 0x80, 0xFF, 0x00, false), (0x40, 0x00, false, true, false, true)),
+            ((0x6B, 0x01, 0xFF, 0x00, false), (0x00, 0x00, false, false, true, false)),
+            // AXS is X = (A & X) - operand, with C like CMP and V untouched
+            ((0xCB, 0x01, 0xF3, 0x3F, false), (0xF3, 0x32, false, false, false, true)),
+            ((0xCB, 0x34, 0xF3, 0x3F, true), (0xF3, 0xFF, true, false, false, false)),
+            ((0xCB, 0x33, 0xF3, 0x3F, false), (0xF3, 0x00, false, false, true, true)),
+        ];
+        for &((code, operand, ac, x, carry), expected) in cases {
+            use self::StatusBit::*;
+            let mut cpu = load(&[code, operand]);
+            cpu.ac = ac;
+            cpu.x = x;
+            cpu.set_status(Carry, carry);
+            cpu.step();
+            let flag = |bit| cpu.status(bit);
+            let outcome = (cpu.ac(), cpu.x(), flag(Negative), flag(Overflow), flag(Zero), flag(Carry));
+            assert_eq!(outcome, expected, "${:02X} #${:02X}", code, operand);
+        }
+    }
+    
+    #[test]
+    fn pha_pla_round_trip() {
+        let mut cpu = load(&[
+            0xA9, 0x80, // LDA #$80
+            0x48,       // PHA
+            0xA9, 0x00, // LDA #$00
+            0x48,       // PHA
+            0xA9, 0x33, // LDA #$33
+            0x68,       // PLA
+            0x68,       // PLA
+        ]);
+        run(&mut cpu, 5);
+        assert_eq!(cpu.sp(), 0xFD);
+        assert_eq!((cpu.bus().read(0x01FF), cpu.bus().read(0x01FE)), (0x80, 0x00));
+        cpu.step();
+        assert_eq!((cpu.ac(), nz(&cpu)), (0x00, (false, true)));
+        cpu.step();
+        assert_eq!((cpu.ac(), nz(&cpu)), (0x80, (true, false)));
+        assert_eq!(cpu.sp(), 0xFF);
+    }
+    
+    /// Zero page memory that records the address of every read.
+    #[derive(Debug)]
+    struct CountingZeroPage {
+        data: [u8; 0x100],
+        reads: std::rc::Rc<std::cell::RefCell<Vec<u16>>>,
+    }
+    
+    impl Backend for CountingZeroPage {
+        fn size(&self) -> u16 {
+            0x100
+        }
+        
+        fn read(&self, addr: u16) -> u8 {
+            self.reads.borrow_mut().push(addr);
+            self.data[addr as usize]
+        }
+        
+        fn write(&mut self, addr: u16, value: u8) {
+            self.data[addr as usize] = value;
+        }
+    }
+    
+    #[test]
+    fn memory_nops_read_their_operand() {
+        use opcode::{Addressing, OpId};
+        for op in (0..=0xFFu8).filter_map(OpCode::get).filter(|op| op.id == OpId::NOP) {
+            let reads = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+            let mut cpu = Cpu::new();
+            // Attached first, so it shadows the bottom of the RAM
+            cpu.attach_backend(BusEntry::new(
+                Box::new(CountingZeroPage { data: [0; 0x100], reads: reads.clone() }),
+                "ZP".into(),
+                0x0000,
+            ));
+            cpu.attach_backend(Ram::new_entry());
+            let operand = match op.addressing {
+                Addressing::AbsoluteX | Addressing::ZeroPageX => 0x0C,
+                _ => 0x10,
+            };
+            cpu.bus_mut().load(ORIGIN, &[op.code, operand, 0x00]);
+            cpu.pc = ORIGIN;
+            cpu.x = 0x04;
+            cpu.step();
+            let expected: &[u16] = match op.addressing {
+                Addressing::Implied | Addressing::Immediate => &[],
+                _ => &[0x0010],
+            };
+            assert_eq!(*reads.borrow(), expected, "${:02X}", op.code);
+        }
+    }
+    
+    #[test]
+    fn kil_halts_until_reset() {
+        let mut cpu = load(&[0xA9, 0x01, 0x02, 0xA9, 0x02]);
+        run(&mut cpu, 2);
+        assert!(cpu.is_halted() && cpu.is_jammed() && !cpu.is_stopped());
+        // PC stays on the jam opcode, and stepping does nothing
+        assert_eq!(cpu.pc(), 0x0202);
+        let sr = cpu.sr();
+        run(&mut cpu, 10);
+        assert_eq!((cpu.pc(), cpu.ac(), cpu.sr()), (0x0202, 0x01, sr));
+        assert!(format!("{:?}", cpu).contains(", state: jammed, cycles: 4, instructions: 2, next: KIL,"));
+        
+        cpu.reset();
+        assert!(!cpu.is_halted() && !cpu.is_jammed());
+        assert_eq!(cpu.pc(), ORIGIN);
+        assert!(format!("{:?}", cpu).contains(", state: running, cycles: 4, instructions: 2, next: LDA #$01,"));
+        run(&mut cpu, 2);
+        assert!(cpu.is_halted());
+    }
+    
+    /// BRA over a NOP, then STZ $0300 and STZ $0300,X. On an NMOS part $80
+    /// is a two-byte NOP and $9C has no stable meaning.
+    const BRA_STZ: &[u8] = &[0x80, 0x01, 0xEA, 0x9C, 0x00, 0x03, 0x9E, 0x00, 0x03];
+    
+    #[test]
+    fn cmos_runs_bra_and_stz() {
+        let mut cpu = load_at(Variant::Cmos, ORIGIN, BRA_STZ);
+        cpu.bus_mut().load(0x0300, &[0xAA, 0xBB, 0xCC]);
+        cpu.x = 0x01;
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x0203);
+        run(&mut cpu, 2);
+        assert_eq!(cpu.pc(), 0x0209);
+        let memory: Vec<u8> = (0x0300..0x0303).map(|addr| cpu.bus().read(addr)).collect();
+        assert_eq!(memory, [0x00, 0x00, 0xCC]);
+    }
+    
+    #[test]
+    #[should_panic(expected = "Unsupported unstable opcode at 0x203: 0x9c (SHY)")]
+    fn nmos_rejects_stz() {
+        assert!(!OpCode::get(0x9C).unwrap().stable);
+        assert!(!OpCode::get(0x9E).unwrap().stable);
+        let mut cpu = load(BRA_STZ);
+        run(&mut cpu, 2);
+        assert_eq!(cpu.pc(), 0x0203);
+        cpu.step();
+    }
+    
+    #[test]
+    fn cmos_reserved_opcodes_are_nops() {
+        use opcode::OpId;
+        let mut lengths = [0; 4];
+        for code in 0..=0xFFu8 {
+            let op = OpCode::get_for(Variant::Cmos, code).unwrap();
+            if op.id != OpId::NOP || op.code == 0xEA {
+                continue;
+            }
+            lengths[op.bytes as usize] += 1;
+            let mut cpu = load_at(Variant::Cmos, ORIGIN, &[code, 0x10, 0x03]);
+            cpu.bus_mut().write(0x0010, 0x55);
+            cpu.ac = 0x12;
+            cpu.x = 0x04;
+            cpu.y = 0x56;
+            cpu.sr = 0xE3;
+            cpu.step();
+            assert_eq!(cpu.pc(), ORIGIN + op.bytes as u16, "${:02X}", code);
+            assert_eq!((cpu.ac(), cpu.x(), cpu.y(), cpu.sp(), cpu.sr()), (0x12, 0x04, 0x56, 0xFF, 0xE3), "${:02X}", code);
+            assert_eq!(cpu.bus().read(0x0010), 0x55);
+        }
+        // 30 one-byte, 11 two-byte and 3 three-byte reserved encodings
+        assert_eq!(lengths, [0, 30, 11, 3]);
+    }
+    
+    #[test]
+    fn cmos_bit_modes() {
+        use self::StatusBit::*;
+        let mut cpu = load_at(Variant::Cmos, ORIGIN, &[
+            0x89, 0xC0,       // BIT #$C0
+            0x89, 0x01,       // BIT #$01
+            0x34, 0x0C,       // BIT $0C,X
+            0x3C, 0x00, 0x03, // BIT $0300,X
+        ]);
+        cpu.bus_mut().write(0x0010, 0xC1);
+        cpu.bus_mut().write(0x0304, 0x40);
+        cpu.ac = 0x01;
+        cpu.x = 0x04;
+        // Immediate only sets Z, leaving N and V as they were
+        cpu.step();
+        assert_eq!(cpu.sr(), 0x26);
+        cpu.set_status(Negative, true);
+        cpu.step();
+        assert_eq!(cpu.sr(), 0xA4);
+        cpu.step();
+        assert_eq!(cpu.sr(), 0xE4);
+        cpu.step();
+        assert_eq!(cpu.sr(), 0x66);
+    }
+    
+    #[test]
+    fn cmos_jmp_absolute_indexed_indirect() {
+        let mut cpu = load_at(Variant::Cmos, ORIGIN, &[0x7C, 0xFE, 0x10]);
+        cpu.x = 0x02;
+        cpu.bus_mut().load(0x1100, &[0x34, 0x12]);
+        assert!(format!("{:?}", cpu).contains("next: JMP ($10FE,X)"));
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x1234);
+    }
+    
+    #[test]
+    fn ror_nine_times_restores_a() {
+        // A and C form a nine-bit ring
+        let mut program = vec![0x38, 0xA9, 0x5A];
+        program.extend_from_slice(&[0x6A; 9]);
+        let mut cpu = load(&program);
+        run(&mut cpu, 3);
+        assert_eq!((cpu.ac(), cpu.status(StatusBit::Carry)), (0xAD, false));
+        run(&mut cpu, 8);
+        assert_eq!((cpu.ac(), cpu.status(StatusBit::Carry)), (0x5A, true));
+        assert_eq!(nz(&cpu), (false, false));
+    }
+    
+    #[test]
+    fn rol_chains_a_sixteen_bit_shift() {
+        let mut cpu = load(&[
+            0x06, 0x10, // ASL $10
+            0x26, 0x11, // ROL $11
+            0x06, 0x10, // ASL $10
+            0x26, 0x11, // ROL $11
+        ]);
+        cpu.bus_mut().load(0x0010, &[0x81, 0x40]);
+        run(&mut cpu, 2);
+        assert_eq!(cpu.bus().read_u16(0x0010), 0x8102);
+        assert_eq!(nz(&cpu), (true, false));
+        assert!(!cpu.status(StatusBit::Carry));
+        run(&mut cpu, 2);
+        assert_eq!(cpu.bus().read_u16(0x0010), 0x0204);
+        assert!(cpu.status(StatusBit::Carry));
+    }
+    
+    #[test]
+    fn rti_ignores_stacked_break() {
+        let mut cpu = load(&[0x40]);
+        // SR with every bit set, then return address $1234
+        cpu.bus_mut().load(0x01FD, &[0xFF, 0x34, 0x12]);
+        cpu.sp = 0xFC;
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x1234);
+        assert_eq!(cpu.sp(), 0xFF);
+        assert_eq!(cpu.sr(), 0xEF);
+        assert!(!cpu.status(StatusBit::Break));
+        
+        // Bit 5 reads back set even when the stacked copy is clear
+        let mut cpu = load(&[0x40]);
+        cpu.bus_mut().load(0x01FD, &[0x10, 0x00, 0x03]);
+        cpu.sp = 0xFC;
+        cpu.step();
+        assert_eq!((cpu.pc(), cpu.sr()), (0x0300, 0x20));
+    }
+    
+    #[test]
+    fn zero_page_indirect_pointer_wraps() {
+        let mut cpu = load_at(Variant::Cmos, ORIGIN, &[
+            0xB2, 0xFF, // LDA ($FF)
+            0x92, 0xFE, // STA ($FE)
+            0x12, 0x10, // ORA ($10)
+        ]);
+        // The pointer at $FF takes its high byte from $00, not $0100
+        cpu.bus_mut().write(0x00FF, 0x40);
+        cpu.bus_mut().write(0x0000, 0x03);
+        cpu.bus_mut().write(0x0100, 0x05);
+        cpu.bus_mut().write(0x0340, 0x81);
+        cpu.bus_mut().write(0x0540, 0x99);
+        cpu.bus_mut().write(0x00FE, 0x00);
+        cpu.bus_mut().write_u16(0x0010, 0x0341);
+        cpu.bus_mut().write(0x0341, 0x02);
+        cpu.step();
+        assert_eq!((cpu.ac(), nz(&cpu)), (0x81, (true, false)));
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x4000), 0x81);
+        cpu.step();
+        assert_eq!(cpu.ac(), 0x83);
+        assert_eq!((cpu.x(), cpu.y()), (0x00, 0x00));
+    }
+    
+    #[test]
+    fn binary_sbc_for_all_inputs() {
+        use self::StatusBit::*;
+        let mut cpu = load(&[]);
+        for a in 0..=0xFFu8 {
+            for b in 0..=0xFFu8 {
+                for &c in &[false, true] {
+                    let borrow = !c as i16;
+                    let unsigned = a as i16 - b as i16 - borrow;
+                    let signed = a as i8 as i16 - b as i8 as i16 - borrow;
+                    let result = unsigned as u8;
+                    let expected = (result, result & 0x80 != 0, !(-128..=127).contains(&signed), result == 0, unsigned >= 0);
+                    
+                    cpu.bus_mut().load(ORIGIN, &[0xE9, b]);
+                    cpu.pc = ORIGIN;
+                    cpu.ac = a;
+                    cpu.sr = 0x20;
+                    cpu.set_status(Carry, c);
+                    cpu.step();
+                    let flag = |bit| cpu.status(bit);
+                    let actual = (cpu.ac(), flag(Negative), flag(Overflow), flag(Zero), flag(Carry));
+                    assert_eq!(actual, expected, "${:02X}-${:02X}-{}", a, b, borrow);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn sbc_borrow_and_overflow() {
+        let sbc = |a, b, carry| {
+            let mut cpu = load(&[if carry { 0x38 } else { 0x18 }, 0xA9, a, 0xE9, b]);
+            run(&mut cpu, 3);
+            use self::StatusBit::*;
+            let flag = |bit| cpu.status(bit);
+            (cpu.ac(), flag(Negative), flag(Overflow), flag(Zero), flag(Carry))
+        };
+        // A, N, V, Z, C
+        assert_eq!(sbc(0x50, 0x30, true), (0x20, false, false, false, true));
+        // A clear carry borrows one more
+        assert_eq!(sbc(0x50, 0x30, false), (0x1F, false, false, false, true));
+        assert_eq!(sbc(0x30, 0x30, true), (0x00, false, false, true, true));
+        assert_eq!(sbc(0x30, 0x30, false), (0xFF, true, false, false, false));
+        assert_eq!(sbc(0x00, 0x01, true), (0xFF, true, false, false, false));
+        // Signed overflow both ways
+        assert_eq!(sbc(0x80, 0x01, true), (0x7F, false, true, false, true));
+        assert_eq!(sbc(0x7F, 0xFF, true), (0x80, true, true, false, false));
+        assert_eq!(sbc(0x50, 0xB0, true), (0xA0, true, true, false, false));
+        assert_eq!(sbc(0xD0, 0x70, true), (0x60, false, true, false, true));
+        // Positive minus negative that still fits, but borrows
+        assert_eq!(sbc(0x50, 0xF0, true), (0x60, false, false, false, false));
+    }
+    
+    #[test]
+    fn sbc_borrow_chains_into_high_byte() {
+        // $0150 - $00F0 and $0000 - $0001, low byte first
+        for &(a, b, result, carry) in &[(0x0150u16, 0x00F0u16, 0x0060u16, true), (0x0000, 0x0001, 0xFFFF, false)] {
+            let mut cpu = load(&[
+                0x38,                   // SEC
+                0xA9, a as u8,          // LDA #lo(a)
+                0xE9, b as u8,          // SBC #lo(b)
+                0x85, 0x10,             // STA $10
+                0xA9, (a >> 8) as u8,   // LDA #hi(a)
+                0xE9, (b >> 8) as u8,   // SBC #hi(b)
+                0x85, 0x11,             // STA $11
+            ]);
+            run(&mut cpu, 4);
+            // The low byte borrowed in both cases
+            assert!(!cpu.status(StatusBit::Carry));
+            run(&mut cpu, 3);
+            assert_eq!(cpu.bus().read_u16(0x0010), result, "${:04X}-${:04X}", a, b);
+            assert_eq!(cpu.status(StatusBit::Carry), carry, "${:04X}-${:04X}", a, b);
+        }
+    }
+    
+    #[test]
+    fn rmb_and_smb_each_bit() {
+        for bit in 0..8u8 {
+            let mut cpu = load_at(Variant::Cmos, ORIGIN, &[0x87 | (bit << 4), 0x10, 0x07 | (bit << 4), 0x11]);
+            cpu.bus_mut().load(0x0010, &[0x00, 0xFF]);
+            let sr = cpu.sr();
+            run(&mut cpu, 2);
+            assert_eq!(cpu.bus().read(0x0010), 1 << bit, "SMB{}", bit);
+            assert_eq!(cpu.bus().read(0x0011), !(1 << bit), "RMB{}", bit);
+            assert_eq!(cpu.sr(), sr);
+        }
+    }
+    
+    #[test]
+    fn bbr_and_bbs_each_bit() {
+        for bit in 0..8u8 {
+            for &set in &[false, true] {
+                let value = if set { 1 << bit } else { !(1 << bit) };
+                // BBR then BBS, each skipping 4 bytes forward if taken
+                let mut cpu = load_at(Variant::Cmos, ORIGIN, &[0x0F | (bit << 4), 0x10, 0x04]);
+                cpu.bus_mut().write(0x0010, value);
+                cpu.step();
+                assert_eq!(cpu.pc(), if set { 0x0203 } else { 0x0207 }, "BBR{} on ${:02X}", bit, value);
+                
+                let mut cpu = load_at(Variant::Cmos, ORIGIN, &[0x8F | (bit << 4), 0x10, 0x04]);
+                cpu.bus_mut().write(0x0010, value);
+                cpu.step();
+                assert_eq!(cpu.pc(), if set { 0x0207 } else { 0x0203 }, "BBS{} on ${:02X}", bit, value);
+            }
+        }
+    }
+    
+    #[test]
+    fn bbr_and_bbs_backward() {
+        // $0210: BBS3 $10,-$10 lands on $0203
+        let mut cpu = load_at(Variant::Cmos, 0x0210, &[0xBF, 0x10, 0xF0]);
+        cpu.bus_mut().write(0x0010, 0x08);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x0203);
+        
+        // $0210: BBR7 $10,-$80 lands on $0193
+        let mut cpu = load_at(Variant::Cmos, 0x0210, &[0x7F, 0x10, 0x80]);
+        cpu.bus_mut().write(0x0010, 0x7F);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x0193);
+    }
+    
+    /// CLI or SEI, WAI, INX on a 65C02 with IRQ and NMI handlers at $0300
+    /// and $0310.
+    fn wai_program(mask: bool) -> Cpu {
+        let mut cpu = load_at(Variant::Cmos, ORIGIN, &[if mask { 0x78 } else { 0x58 }, 0xCB, 0xE8]);
+        cpu.bus_mut().load(0xFFFA, &[0x10, 0x03]);
+        cpu.bus_mut().load(0xFFFE, &[0x00, 0x03]);
+        cpu
+    }
+    
+    #[test]
+    fn wai_then_irq_enters_handler() {
+        let mut cpu = wai_program(false);
+        run(&mut cpu, 2);
+        assert!(cpu.is_waiting());
+        assert!(format!("{:?}", cpu).contains(", state: waiting,"));
+        run(&mut cpu, 5);
+        assert_eq!((cpu.pc(), cpu.x()), (0x0202, 0x00));
+        cpu.irq();
+        assert!(!cpu.is_waiting());
+        assert_eq!(cpu.pc(), 0x0300);
+        // The handler returns to the INX after WAI
+        assert_eq!((cpu.bus().read(0x01FF), cpu.bus().read(0x01FE)), (0x02, 0x02));
+    }
+    
+    #[test]
+    fn wai_with_irq_masked_resumes() {
+        let mut cpu = wai_program(true);
+        run(&mut cpu, 2);
+        assert!(cpu.is_waiting());
+        cpu.irq();
+        assert!(!cpu.is_waiting());
+        assert_eq!((cpu.pc(), cpu.sp()), (0x0202, 0xFF));
+        cpu.step();
+        assert_eq!((cpu.pc(), cpu.x()), (0x0203, 0x01));
+    }
+    
+    #[test]
+    fn wai_ended_by_nmi() {
+        let mut cpu = wai_program(true);
+        run(&mut cpu, 2);
+        cpu.nmi();
+        assert!(!cpu.is_waiting());
+        assert_eq!(cpu.pc(), 0x0310);
+    }
+    
+    #[test]
+    fn stp_ignores_interrupts_until_reset() {
+        let mut cpu = load_at(Variant::Cmos, ORIGIN, &[0x58, 0xDB, 0xE8]);
+        cpu.bus_mut().load(0xFFFA, &[0x10, 0x03]);
+        cpu.bus_mut().load(0xFFFE, &[0x00, 0x03]);
+        run(&mut cpu, 2);
+        assert!(cpu.is_stopped() && cpu.is_halted() && !cpu.is_jammed());
+        assert!(!cpu.is_waiting());
+        assert!(format!("{:?}", cpu).contains(", state: stopped, cycles: 5, instructions: 2,"));
+        cpu.irq();
+        cpu.nmi();
+        run(&mut cpu, 3);
+        assert_eq!((cpu.pc(), cpu.sp(), cpu.x()), (0x0202, 0xFF, 0x00));
+        assert!(cpu.is_stopped());
+        assert_eq!(cpu.instructions(), 2);
+        
+        cpu.reset();
+        assert!(!cpu.is_stopped() && !cpu.is_halted());
+        assert_eq!(cpu.pc(), ORIGIN);
+    }
+    
+    #[test]
+    fn reset_ends_wai() {
+        let mut cpu = wai_program(false);
+        run(&mut cpu, 2);
+        assert!(cpu.is_waiting());
+        cpu.reset();
+        assert!(!cpu.is_waiting() && !cpu.is_stopped());
+        assert_eq!(cpu.pc(), ORIGIN);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x0201);
+    }
+    
+    #[test]
+    fn set_and_clear_flag_instructions() {
+        let mut cpu = load(&[
+            0x58, // CLI
+            0x38, // SEC
+            0xF8, // SED
+            0x78, // SEI
+            0x18, // CLC
+            0xD8, // CLD
+            0x58, // CLI
+        ]);
+        let expected = [0x20, 0x21, 0x29, 0x2D, 0x2C, 0x24, 0x20];
+        for &sr in &expected {
+            cpu.step();
+            assert_eq!(cpu.sr(), sr, "at ${:04X}", cpu.pc());
+        }
+        // Setting a flag that's already set changes nothing
+        let mut cpu = load(&[0x38, 0x38, 0xF8, 0xF8, 0x78]);
+        run(&mut cpu, 5);
+        assert_eq!(cpu.sr(), 0x2D);
+        
+        // From an all-set SR each one changes only its own bit
+        let toggles = [
+            (0x18, 0x01, false), // CLC
+            (0x38, 0x01, true),  // SEC
+            (0x58, 0x04, false), // CLI
+            (0x78, 0x04, true),  // SEI
+            (0xD8, 0x08, false), // CLD
+            (0xF8, 0x08, true),  // SED
+            (0xB8, 0x40, false), // CLV
+        ];
+        let mut cpu = load(&toggles.iter().map(|&(code, _, _)| code).collect::<Vec<_>>());
+        cpu.sr = 0xFF;
+        for &(code, bit, set) in &toggles {
+            let sr = cpu.sr();
+            cpu.step();
+            let expected = if set { sr | bit } else { sr & !bit };
+            assert_eq!(cpu.sr(), expected, "${:02X} from ${:02X}", code, sr);
+        }
+        assert_eq!(cpu.sr(), 0xBF);
+    }
+    
+    #[test]
+    fn clv_clears_only_overflow() {
+        for &sr in &[0xFF, 0xEF, 0x60] {
+            let mut cpu = load(&[0xB8]);
+            cpu.sr = sr;
+            cpu.step();
+            assert_eq!(cpu.sr(), sr & !0x40, "from ${:02X}", sr);
+            assert!(!cpu.status(StatusBit::Overflow));
+        }
+    }
+    
+    const VARIANTS: [Variant; 3] = [Variant::Nmos, Variant::Cmos, Variant::Rp2A03];
+    
+    #[test]
+    fn decimal_mode_per_variant() {
+        // SED, CLC, LDA #$09, ADC #$01
+        for &variant in &VARIANTS {
+            let mut cpu = load_at(variant, ORIGIN, &[0xF8, 0x18, 0xA9, 0x09, 0x69, 0x01]);
+            run(&mut cpu, 4);
+            // The 2A03 has the D flag but no decimal adder
+            let expected = if variant == Variant::Rp2A03 { 0x0A } else { 0x10 };
+            assert_eq!(cpu.ac(), expected, "{:?}", variant);
+            assert!(cpu.status(StatusBit::Decimal));
+        }
+    }
+    
+    #[test]
+    fn indirect_jmp_page_bug_per_variant() {
+        for &variant in &VARIANTS {
+            let mut cpu = load_at(variant, ORIGIN, &[0x6C, 0xFF, 0x10]);
+            cpu.bus_mut().write(0x10FF, 0x34);
+            cpu.bus_mut().write(0x1000, 0x12);
+            cpu.bus_mut().write(0x1100, 0x56);
+            cpu.step();
+            let expected = if variant == Variant::Cmos { 0x5634 } else { 0x1234 };
+            assert_eq!(cpu.pc(), expected, "{:?}", variant);
+        }
+    }
+    
+    #[test]
+    fn interrupt_entry_clears_decimal_on_cmos_only() {
+        for &variant in &VARIANTS {
+            let mut cpu = load_at(variant, ORIGIN, &[0xF8, 0x00, 0x00]);
+            cpu.bus_mut().load(0xFFFE, &[0x00, 0x03]);
+            run(&mut cpu, 2);
+            assert_eq!(cpu.pc(), 0x0300);
+            // The stacked copy keeps D either way
+            assert_eq!(cpu.bus().read(0x01FD), 0x3C, "{:?}", variant);
+            assert_eq!(cpu.status(StatusBit::Decimal), variant != Variant::Cmos, "{:?}", variant);
+        }
+        for &variant in &VARIANTS {
+            let mut cpu = load_at(variant, ORIGIN, &[0xF8]);
+            cpu.bus_mut().load(0xFFFA, &[0x00, 0x03]);
+            cpu.step();
+            cpu.nmi();
+            assert_eq!(cpu.status(StatusBit::Decimal), variant != Variant::Cmos, "{:?}", variant);
+        }
+    }
+    
+    #[test]
+    fn php_bit_layout() {
+        // Setup before PHP, and the byte it pushes: NV1BDIZC with B and bit 5 set
+        let cases: &[(&[u8], u8)] = &[
+            (&[0x38, 0xA9, 0x80], 0xB5),       // SEC, LDA #$80
+            (&[0x58], 0x30),                   // CLI
+            (&[0xA9, 0x00], 0x36),             // LDA #$00
+            (&[0x38], 0x35),                   // SEC
+            (&[0xF8, 0x58], 0x38),             // SED, CLI
+            (&[0xA9, 0x40, 0x69, 0x40], 0xF4), // LDA #$40, ADC #$40
+            (&[0x38, 0xF8, 0xA9, 0xFF], 0xBD), // SEC, SED, LDA #$FF
+        ];
+        for &(setup, expected) in cases {
+            let mut program = setup.to_vec();
+            program.push(0x08);
+            let mut cpu = load(&program);
+            while cpu.pc() != ORIGIN + program.len() as u16 {
+                cpu.step();
+            }
+            assert_eq!(cpu.bus().read(0x01FF), expected, "{:02X?}", setup);
+            // B only exists in the pushed copy
+            assert_eq!(cpu.sr() | 0x30, expected);
+            assert!(!cpu.status(StatusBit::Break));
+        }
+    }
+    
+    #[test]
+    fn sta_round_trip_in_every_mode() {
+        // Mode 2 would be STA #, which doesn't exist
+        for &mode in &[0, 1, 3, 4, 5, 6, 7] {
+            for &value in &[0x00, 0x7F, 0x80, 0xFF] {
+                let (mut cpu, target) = group_one(0x81, mode, 0xEE);
+                let target = target.unwrap();
+                cpu.ac = value;
+                cpu.sr = 0xE7;
+                cpu.step();
+                assert_eq!(cpu.bus().read(target), value, "mode {}", mode);
+                assert_eq!(cpu.sr(), 0xE7, "mode {}", mode);
+                
+                // Read it back through the same mode
+                cpu.bus_mut().write(ORIGIN + 4, 0xA1 | (mode << 2));
+                cpu.pc = ORIGIN + 4;
+                cpu.ac = !value;
+                cpu.step();
+                assert_eq!(cpu.ac(), value, "mode {}", mode);
+            }
+        }
+    }
+    
+    #[test]
+    fn jmp_indirect_page_wrap() {
+        let setup = |cpu: &mut Cpu| {
+            cpu.bus_mut().write(0x10FF, 0x34);
+            cpu.bus_mut().write(0x1000, 0x12);
+            cpu.bus_mut().write(0x1100, 0x56);
+        };
+        let mut cpu = load(&[0x6C, 0xFF, 0x10]);
+        setup(&mut cpu);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x1234);
+        
+        let mut cpu = load_at(Variant::Cmos, ORIGIN, &[0x6C, 0xFF, 0x10]);
+        setup(&mut cpu);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0x5634);
+        
+        // Pointers that don't end a page are read normally
+        let mut cpu = load(&[0x6C, 0xFE, 0x10]);
+        cpu.bus_mut().write_u16(0x10FE, 0xABCD);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0xABCD);
+    }
+    
+    #[test]
+    fn stx_and_sty_in_every_mode() {
+        let cases: &[(&[u8], u16)] = &[
+            (&[0x86, 0x44], 0x0044),        // STX $44
+            (&[0x96, 0x40], 0x0044),        // STX $40,Y
+            (&[0x8E, 0x44, 0x03], OPERAND), // STX $0344
+            (&[0x84, 0x44], 0x0044),        // STY $44
+            (&[0x94, 0x40], 0x0044),        // STY $40,X
+            (&[0x8C, 0x44, 0x03], OPERAND), // STY $0344
+        ];
+        for &(program, target) in cases {
+            for &value in &[0x00, 0x7F, 0xFF] {
+                let stores_x = program[0] & 0x03 == 0x02;
+                let mut cpu = load(program);
+                cpu.bus_mut().write(target, 0xEE);
+                // The other register indexes, so the result shows which was stored
+                if stores_x {
+                    cpu.x = value;
+                    cpu.y = 0x04;
+                } else {
+                    cpu.x = 0x04;
+                    cpu.y = value;
+                }
+                cpu.sr = 0x20;
+                cpu.step();
+                assert_eq!(cpu.bus().read(target), value, "{:02X?}", program);
+                assert_eq!(cpu.sr(), 0x20, "{:02X?}", program);
+                assert_eq!(cpu.pc(), ORIGIN + program.len() as u16);
+            }
+        }
+    }
+    
+    #[test]
+    fn register_transfers_set_n_and_z() {
+        // (opcode, source register index: 0 = A, 1 = X, 2 = Y, destination index)
+        let transfers = [(0xAA, 0, 1), (0xA8, 0, 2), (0x8A, 1, 0), (0x98, 2, 0)];
+        for &(code, from, to) in &transfers {
+            for &value in &[0x00, 0x80, 0x01] {
+                let mut cpu = load(&[code]);
+                let mut regs = [0x11, 0x22, 0x33];
+                regs[from] = value;
+                cpu.ac = regs[0];
+                cpu.x = regs[1];
+                cpu.y = regs[2];
+                // C, V and I set so any change shows up
+                cpu.sr = 0x65;
+                cpu.step();
+                regs[to] = value;
+                assert_eq!([cpu.ac(), cpu.x(), cpu.y()], regs, "${:02X} with ${:02X}", code, value);
+                assert_eq!(cpu.sr(), with_nz(0x65, value), "${:02X} with ${:02X}", code, value);
+            }
+        }
+    }
+    
+    #[test]
+    fn indirect_pointers_wrap_in_zero_page() {
+        let mut cpu = load(&[
+            0xA2, 0x01, // LDX #$01
+            0xA0, 0x01, // LDY #$01
+            0xA1, 0xFE, // LDA ($FE,X)
+            0xB1, 0xFF, // LDA ($FF),Y
+            0xA2, 0x81, // LDX #$81
+            0x81, 0x7F, // STA ($7F,X)
+        ]);
+        // A pointer at $FF takes its high byte from $00; $0100 is a decoy
+        cpu.bus_mut().write(0x00FF, 0x40);
+        cpu.bus_mut().write(0x0000, 0x03);
+        cpu.bus_mut().write(0x0100, 0x05);
+        cpu.bus_mut().write(0x0340, 0x11);
+        cpu.bus_mut().write(0x0341, 0x22);
+        cpu.bus_mut().write(0x0540, 0x99);
+        cpu.bus_mut().write(0x0541, 0x99);
+        run(&mut cpu, 3);
+        assert_eq!(cpu.ac(), 0x11);
+        cpu.step();
+        assert_eq!(cpu.ac(), 0x22);
+        // $7F + $81 wraps to the pointer at $00, high byte from $01
+        cpu.bus_mut().write(0x0001, 0x04);
+        run(&mut cpu, 2);
+        assert_eq!(cpu.bus().read(0x0403), 0x22);
+    }
+    
+    #[test]
+    fn txs_leaves_flags_and_tsx_sets_them() {
+        let mut cpu = load(&[0xA2, 0x42, 0x9A, 0xA2, 0x00, 0x9A, 0xBA]);
+        cpu.step();
+        let sr = cpu.sr();
+        cpu.step();
+        assert_eq!((cpu.sp(), cpu.sr()), (0x42, sr));
+        // TXS of zero doesn't touch Z either
+        run(&mut cpu, 2);
+        assert_eq!(cpu.sp(), 0x00);
+        assert_eq!(nz(&cpu), (false, true));
+        
+        for &(sp, flags) in &[(0x00, (false, true)), (0x7F, (false, false)), (0x80, (true, false)), (0xFF, (true, false))] {
+            let mut cpu = load(&[0xBA]);
+            cpu.sp = sp;
+            cpu.step();
+            assert_eq!((cpu.x(), nz(&cpu)), (sp, flags));
+        }
+    }
+    
+    #[test]
+    fn indexed_addresses_wrap_past_top_of_memory() {
+        let mut cpu = load(&[
+            0xA2, 0x20,       // LDX #$20
+            0xBD, 0xF0, 0xFF, // LDA $FFF0,X
+            0xA0, 0x21,       // LDY #$21
+            0x99, 0xF0, 0xFF, // STA $FFF0,Y
+            0xFE, 0xF0, 0xFF, // INC $FFF0,X
+        ]);
+        cpu.bus_mut().write(0x0010, 0x5A);
+        run(&mut cpu, 2);
+        assert_eq!(cpu.ac(), 0x5A);
+        run(&mut cpu, 2);
+        assert_eq!(cpu.bus().read(0x0011), 0x5A);
+        cpu.step();
+        assert_eq!(cpu.bus().read(0x0010), 0x5B);
+    }
+    
+    #[test]
+    fn branch_backwards_from_bottom_of_memory() {
+        // BNE -4 at $0000 lands on $FFFE
+        let mut cpu = load_at(Variant::Nmos, 0x0000, &[0xD0, 0xFC]);
+        cpu.step();
+        assert_eq!(cpu.pc(), 0xFFFE);
+    }
+    
+    #[test]
+    fn increments_decrements_and_transfers_at_boundaries() {
+        const INPUTS: [u8; 4] = [0x00, 0xFF, 0x7F, 0x80];
+        // For each input above: result, N, Z
+        type Results = [(u8, bool, bool); 4];
+        const INC: Results = [(0x01, false, false), (0x00, false, true), (0x80, true, false), (0x81, true, false)];
+        const DEC: Results = [(0xFF, true, false), (0xFE, true, false), (0x7E, false, false), (0x7F, false, false)];
+        const COPY: Results = [(0x00, false, true), (0xFF, true, false), (0x7F, false, false), (0x80, true, false)];
+        // Registers: 0 = A, 1 = X, 2 = Y, 3 = SP, 4 = memory at $10
+        let ops: &[(&[u8], usize, usize, &Results)] = &[
+            (&[0xE8], 1, 1, &INC),       // INX
+            (&[0xC8], 2, 2, &INC),       // INY
+            (&[0xCA], 1, 1, &DEC),       // DEX
+            (&[0x88], 2, 2, &DEC),       // DEY
+            (&[0xE6, 0x10], 4, 4, &INC), // INC $10
+            (&[0xC6, 0x10], 4, 4, &DEC), // DEC $10
+            (&[0xAA], 0, 1, &COPY),      // TAX
+            (&[0xA8], 0, 2, &COPY),      // TAY
+            (&[0x8A], 1, 0, &COPY),      // TXA
+            (&[0x98], 2, 0, &COPY),      // TYA
+            (&[0xBA], 3, 1, &COPY),      // TSX
+        ];
+        for &(program, from, to, table) in ops {
+            for (&input, &(result, n, z)) in INPUTS.iter().zip(table.iter()) {
+                let mut cpu = load(program);
+                let mut regs = [0x11, 0x22, 0x33, 0x44, 0x55];
+                regs[from] = input;
+                cpu.ac = regs[0];
+                cpu.x = regs[1];
+                cpu.y = regs[2];
+                cpu.sp = regs[3];
+                cpu.bus_mut().write(0x0010, regs[4]);
+                cpu.sr = 0x61;
+                cpu.step();
+                regs[to] = result;
+                let actual = [cpu.ac(), cpu.x(), cpu.y(), cpu.sp(), cpu.bus().read(0x0010)];
+                assert_eq!(actual, regs, "{:02X?} on ${:02X}", program, input);
+                assert_eq!(nz(&cpu), (n, z), "{:02X?} on ${:02X}", program, input);
+                // C and V are never touched
+                assert_eq!(cpu.sr() & 0x41, 0x41);
+            }
+        }
+    }
+    
+    #[test]
+    fn bvc_and_bvs() {
+        // (opcode, V, displacement) => PC after the branch at $0210
+        let cases = [
+            ((0x50, false, 0x10), 0x0222), // BVC taken forward
+            ((0x50, false, 0xF0), 0x0202), // BVC taken backward
+            ((0x50, true, 0x10), 0x0212),  // BVC not taken
+            ((0x70, true, 0x10), 0x0222),  // BVS taken forward
+            ((0x70, true, 0xF0), 0x0202),  // BVS taken backward
+            ((0x70, false, 0xF0), 0x0212), // BVS not taken
+        ];
+        for &((code, overflow, displacement), pc) in &cases {
+            let mut cpu = load_at(Variant::Nmos, 0x0210, &[code, displacement]);
+            cpu.set_status(StatusBit::Overflow, overflow);
+            let sr = cpu.sr();
+            cpu.step();
+            assert_eq!(cpu.pc(), pc, "${:02X} ${:02X} with V={}", code, displacement, overflow);
+            assert_eq!(cpu.sr(), sr);
+        }
+        
+        // V set by ADC and cleared by CLV, as a program would
+        let mut cpu = load(&[0xA9, 0x7F, 0x69, 0x01, 0x70, 0x01, 0x00, 0xB8, 0x50, 0xFE]);
+        run(&mut cpu, 3);
+        assert_eq!(cpu.pc(), 0x0207);
+        run(&mut cpu, 2);
+        assert_eq!(cpu.pc(), 0x0208);
+        
+        // V copied from bit 6 of a BIT operand, starting from the opposite V
+        let bits: [&[u8]; 2] = [&[0x24, 0x10], &[0x2C, 0x00, 0x03]];
+        for &(value, overflow) in &[(0x40, true), (0x00, false)] {
+            for bit in &bits {
+                for &branch in &[0x50, 0x70] {
+                    let mut program = bit.to_vec();
+                    program.extend_from_slice(&[branch, 0x02]);
+                    let mut cpu = load(&program);
+                    cpu.bus_mut().write(0x0010, value);
+                    cpu.bus_mut().write(0x0300, value);
+                    cpu.set_status(StatusBit::Overflow, !overflow);
+                    run(&mut cpu, 2);
+                    let taken = (branch == 0x70) == overflow;
+                    let pc = ORIGIN + program.len() as u16 + if taken { 2 } else { 0 };
+                    assert_eq!(cpu.pc(), pc, "${:02X} then ${:02X} on ${:02X}", bit[0], branch, value);
+                    assert_eq!(cpu.status(StatusBit::Overflow), overflow);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn nmi_enters_handler_even_with_i_set() {
+        let mut cpu = load(&[0x58, 0x38, 0xEA]);
+        cpu.bus_mut().load(0xFFFA, &[0x00, 0x03]);
+        run(&mut cpu, 2);
+        cpu.nmi();
+        assert_eq!(cpu.pc(), 0x0300);
+        assert_eq!(cpu.sp(), 0xFC);
+        assert_eq!((cpu.bus().read(0x01FF), cpu.bus().read(0x01FE)), (0x02, 0x02));
+        // B clear in the stacked copy, and I set on entry
+        assert_eq!(cpu.bus().read(0x01FD), 0x21);
+        assert!(cpu.status(StatusBit::Interrupt));
+        
+        // A second NMI isn't masked by the I flag the first one set
+        cpu.nmi();
+        assert_eq!(cpu.pc(), 0x0300);
+        assert_eq!(cpu.sp(), 0xF9);
+        assert_eq!((cpu.bus().read(0x01FC), cpu.bus().read(0x01FB)), (0x03, 0x00));
+        assert_eq!(cpu.bus().read(0x01FA), 0x25);
+    }
+    
+    #[test]
+    fn instruction_fetch_wraps_past_top_of_memory() {
+        // LDA $1234 with the opcode at $FFFE, so the operand is $FFFF and $0000
+        let mut cpu = load_at(Variant::Nmos, 0xFFFE, &[0xAD, 0x34, 0x12, 0xE8]);
+        cpu.bus_mut().write(0x1234, 0x77);
+        assert!(format!("{:?}", cpu).contains("next: LDA $1234"));
+        cpu.step();
+        assert_eq!((cpu.ac(), cpu.pc()), (0x77, 0x0001));
+        cpu.step();
+        assert_eq!((cpu.x(), cpu.pc()), (0x01, 0x0002));
+    }
+    
+    #[test]
+    fn irq_masked_by_i() {
+        // Reset leaves I set
+        let mut cpu = load(&[0xEA, 0xEA]);
+        cpu.bus_mut().load(0xFFFE, &[0x00, 0x03]);
+        cpu.step();
+        cpu.irq();
+        assert_eq!((cpu.pc(), cpu.sp(), cpu.sr()), (0x0201, 0xFF, 0x24));
+    }
+    
+    #[test]
+    fn irq_enters_handler_and_returns() {
+        let mut cpu = load(&[0x58, 0xE8, 0xE8]);
+        cpu.bus_mut().load(0xFFFE, &[0x00, 0x03]);
+        cpu.bus_mut().load(0x0300, &[0xA0, 0x01, 0x40]);
+        run(&mut cpu, 2);
+        cpu.irq();
+        assert_eq!(cpu.pc(), 0x0300);
+        assert_eq!((cpu.bus().read(0x01FF), cpu.bus().read(0x01FE)), (0x02, 0x02));
+        // Bit 4 clear, where BRK would have pushed $30
+        assert_eq!(cpu.bus().read(0x01FD), 0x20);
+        assert!(cpu.status(StatusBit::Interrupt));
+        // A second IRQ is masked while the handler runs
+        cpu.irq();
+        assert_eq!((cpu.pc(), cpu.sp()), (0x0300, 0xFC));
+        
+        run(&mut cpu, 2);
+        assert_eq!((cpu.pc(), cpu.sp(), cpu.sr()), (0x0202, 0xFF, 0x20));
+        cpu.step();
+        assert_eq!((cpu.x(), cpu.y()), (0x02, 0x01));
+    }
+    
+    #[test]
+    fn branch_targets_at_limits() {
+        // (variant, branch address, instruction, target); every branch is taken
+        let cases: &[(Variant, u16, &[u8], u16)] = &[
+            (Variant::Nmos, 0x0210, &[0x10, 0x7F], 0x0291),       // BPL +127
+            (Variant::Nmos, 0x0210, &[0x10, 0x80], 0x0192),       // BPL -128
+            (Variant::Nmos, 0x02F0, &[0x10, 0x20], 0x0312),       // across a page
+            (Variant::Nmos, 0x0310, &[0x10, 0xEC], 0x02FE),       // back across one
+            (Variant::Nmos, 0xFFF0, &[0x10, 0x7F], 0x0071),       // past $FFFF
+            (Variant::Nmos, 0x0010, &[0x10, 0x80], 0xFF92),       // below $0000
+            (Variant::Cmos, 0x0210, &[0x80, 0x7F], 0x0291),       // BRA +127
+            (Variant::Cmos, 0xFFF0, &[0x80, 0x7F], 0x0071),       // BRA past $FFFF
+            (Variant::Cmos, 0x0210, &[0x0F, 0x80, 0x7F], 0x0292), // BBR0 +127
+            (Variant::Cmos, 0x0210, &[0x0F, 0x80, 0x80], 0x0193), // BBR0 -128
+            (Variant::Cmos, 0x02F0, &[0x0F, 0x80, 0x20], 0x0313), // BBR0 across a page
+            (Variant::Cmos, 0xFFF0, &[0x0F, 0x80, 0x7F], 0x0072), // BBR0 past $FFFF
+            (Variant::Cmos, 0x0010, &[0xFF, 0x90, 0x80], 0xFF93), // BBS7 below $0000
+        ];
+        for &(variant, at, program, target) in cases {
+            let mut cpu = load_at(variant, at, program);
+            cpu.bus_mut().write(0x0080, 0x00);
+            cpu.bus_mut().write(0x0090, 0x80);
+            cpu.step();
+            assert_eq!(cpu.pc(), target, "{:02X?} at ${:04X}", program, at);
+        }
+    }
+    
+    #[test]
+    fn cycles_and_elapsed_time() {
+        // LDA #$01 (2), STA $0300 (4), INC $0300,X (7), JMP $0200 (3)
+        let mut cpu = load(&[0xA9, 0x01, 0x8D, 0x00, 0x03, 0xFE, 0x00, 0x03, 0x4C, 0x00, 0x02]);
+        cpu.bus_mut().load(0xFFFA, &[0x00, 0x02]);
+        assert_eq!((cpu.cycles(), cpu.elapsed()), (0, None));
+        run(&mut cpu, 4);
+        assert_eq!(cpu.cycles(), 16);
+        cpu.nmi();
+        assert_eq!(cpu.cycles(), 23);
+        
+        cpu.set_clock_rate(Some(ClockRate::MHZ_8));
+        assert_eq!(cpu.clock_rate(), Some(ClockRate::MHZ_8));
+        assert_eq!(cpu.elapsed(), Some(Duration::from_nanos(2_875)));
+        cpu.set_clock_rate(Some(ClockRate::from_hz(1_000_000)));
+        assert_eq!(cpu.elapsed(), Some(Duration::from_micros(23)));
+        // A full second's worth of NOPs at 1 MHz
+        let mut cpu = load(&[0x4C, 0x00, 0x02]);
+        cpu.set_clock_rate(Some(ClockRate::from_hz(1_000_000)));
+        run(&mut cpu, 1_000_000 / 3);
+        assert_eq!(cpu.elapsed(), Some(Duration::from_micros(999_999)));
+        
+        cpu.set_clock_rate(None);
+        assert_eq!(cpu.elapsed(), None);
+    }
 }
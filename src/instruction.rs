@@ -1,4 +1,4 @@
-use opcode::OpCode;
+use opcode::{OpCode, OpId};
 use std::fmt;
 
 pub struct Instruction {
@@ -10,22 +10,31 @@ impl fmt::Debug for Instruction {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         use opcode::Addressing::*;
         match self.opcode.bytes {
-            1 => write!(fmt, "{:?}", self.opcode.id),
+            1 => match self.opcode.addressing {
+                Accumulator => write!(fmt, "{:?} A", self.opcode.id),
+                _ => write!(fmt, "{:?}", self.opcode.id),
+            },
             x => {
-                let width = (x - 1) * 2;
+                let width = (x - 1) as usize * 2;
+                let id = self.opcode.id;
+                let op = self.operand;
+                if let OpId::RMB | OpId::SMB = id {
+                    return write!(fmt, "{:?}{} ${:02X}", id, self.opcode.bit(), op);
+                }
                 match self.opcode.addressing {
-                    Absolute => write!(fmt, "{0:01$x}", self.operand, width as usize),
-                    /*AbsoluteX,
-                    AbsoluteY,
-                    Immediate,
-                    Indirect,
-                    IndirectX,
-                    IndirectY,
-                    Relative,
-                    ZeroPage,
-                    ZeroPageX,
-                    ZeroPageY,*/
-                    _ => write!(fmt, "{:?}", self.opcode.id),
+                    Absolute | ZeroPage | Relative => write!(fmt, "{:?} ${:02$X}", id, op, width),
+                    AbsoluteX | ZeroPageX => write!(fmt, "{:?} ${:02$X},X", id, op, width),
+                    AbsoluteY | ZeroPageY => write!(fmt, "{:?} ${:02$X},Y", id, op, width),
+                    Immediate => write!(fmt, "{:?} #${:02X}", id, op),
+                    Indirect => write!(fmt, "{:?} (${:04X})", id, op),
+                    AbsoluteIndirectX => write!(fmt, "{:?} (${:04X},X)", id, op),
+                    IndirectX => write!(fmt, "{:?} (${:02X},X)", id, op),
+                    IndirectY => write!(fmt, "{:?} (${:02X}),Y", id, op),
+                    ZeroPageIndirect => write!(fmt, "{:?} (${:02X})", id, op),
+                    ZeroPageRelative => {
+                        write!(fmt, "{:?}{} ${:02X},${:02X}", id, self.opcode.bit(), op & 0xFF, op >> 8)
+                    },
+                    _ => write!(fmt, "{:?}", id),
                 }
             }
         }
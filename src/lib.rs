@@ -1,7 +1,7 @@
-#![feature(wrapping)]
-
+pub mod bcd;
 pub mod cpu;
 pub mod bus;
+pub mod clock;
 pub mod memory;
 pub mod instruction;
 pub mod opcode;
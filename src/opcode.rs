@@ -1,64 +1,3 @@
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub enum OpId {
-	ADC = 1,
-	AND,
-	ASL,
-	BCC,
-	BCS,
-	BEQ,
-	BIT,
-	BMI,
-	BNE,
-	BPL,
-	BRK,
-	BVC,
-	BVS,
-	CLC,
-	CLD,
-	CLI,
-	CLV,
-	CMP,
-	CPX,
-	CPY,
-	DEC,
-	DEX,
-	DEY,
-	EOR,
-	INC,
-	INX,
-	INY,
-	JMP,
-	JSR,
-	LDA,
-	LDX,
-	LDY,
-	LSR,
-	NOP,
-	ORA,
-	PHA,
-	PHP,
-	PLA,
-	PLP,
-	ROL,
-	ROR,
-	RTI,
-	RTS,
-	SBC,
-	SEC,
-	SED,
-	SEI,
-	STA,
-	STX,
-	STY,
-	TAX,
-	TAY,
-	TSX,
-	TXA,
-	TXS,
-	TYA,
-    INVALID,
-}
-
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Addressing {
     Implied,
@@ -74,6 +13,24 @@ pub enum Addressing {
     ZeroPage,
     ZeroPageX,
     ZeroPageY,
+    /// `($zp)`, 65C02 only.
+    ZeroPageIndirect,
+    /// A zero page address then a branch displacement, for BBR and BBS.
+    ZeroPageRelative,
+    /// `($abs,X)`, the 65C02's JMP only.
+    AbsoluteIndirectX,
+}
+
+/// Which member of the 6502 family is being emulated.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Variant {
+    /// The original NMOS part, with its stable undocumented opcodes.
+    #[default]
+    Nmos,
+    /// The 65C02, which turns most of those bytes into new instructions.
+    Cmos,
+    /// The NES's Ricoh 2A03, an NMOS core with decimal mode disconnected.
+    Rp2A03,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -83,176 +40,900 @@ pub struct OpCode {
     pub addressing: Addressing,
     pub bytes: u8,
     pub cycles: u8,
+    /// False for the undocumented NMOS encodings.
+    pub documented: bool,
+    /// False for the NMOS encodings whose results depend on the individual
+    /// chip or on bus timing. They decode so tools can name them, but the
+    /// CPU refuses to execute them.
+    pub stable: bool,
 }
 
 impl OpCode {
-    fn new(code: u8, id: OpId, addressing: Addressing, bytes: u8, cycles: u8) -> OpCode {
+    fn new(code: u8, id: OpId, addressing: Addressing, bytes: u8, cycles: u8, documented: bool, stable: bool) -> OpCode {
         OpCode {
-            code: code,
-            id: id,
-            addressing: addressing,
-            bytes: bytes,
-            cycles: cycles,
+            code,
+            id,
+            addressing,
+            bytes,
+            cycles,
+            documented,
+            stable,
         }
     }
     
-    pub fn get(code: u8) -> Option<OpCode> {
-        use self::OpId::*;
-        use self::Addressing::*;
-        match code {
-            0x69 => Some(OpCode::new(0x69, ADC, Immediate, 2, 2)),
-            0x65 => Some(OpCode::new(0x65, ADC, ZeroPage, 2, 3)),
-            0x75 => Some(OpCode::new(0x75, ADC, ZeroPageX, 2, 4)),
-            0x6D => Some(OpCode::new(0x6D, ADC, Absolute, 3, 4)),
-            0x7D => Some(OpCode::new(0x7D, ADC, AbsoluteX, 3, 4)),
-            0x79 => Some(OpCode::new(0x79, ADC, AbsoluteY, 3, 4)),
-            0x61 => Some(OpCode::new(0x61, ADC, IndirectX, 2, 6)),
-            0x71 => Some(OpCode::new(0x71, ADC, IndirectY, 2, 5)),
-            0x29 => Some(OpCode::new(0x29, AND, Immediate, 2, 2)),
-            0x25 => Some(OpCode::new(0x25, AND, ZeroPage, 2, 3)),
-            0x35 => Some(OpCode::new(0x35, AND, ZeroPageX, 2, 4)),
-            0x2D => Some(OpCode::new(0x2D, AND, Absolute, 3, 4)),
-            0x3D => Some(OpCode::new(0x3D, AND, AbsoluteX, 3, 4)),
-            0x39 => Some(OpCode::new(0x39, AND, AbsoluteY, 3, 4)),
-            0x21 => Some(OpCode::new(0x21, AND, IndirectX, 2, 6)),
-            0x31 => Some(OpCode::new(0x31, AND, IndirectY, 2, 5)),
-            0x0A => Some(OpCode::new(0x0A, ASL, Accumulator, 1, 2)),
-            0x06 => Some(OpCode::new(0x06, ASL, ZeroPage, 2, 5)),
-            0x16 => Some(OpCode::new(0x16, ASL, ZeroPageX, 2, 6)),
-            0x0E => Some(OpCode::new(0x0E, ASL, Absolute, 3, 6)),
-            0x1E => Some(OpCode::new(0x1E, ASL, AbsoluteX, 3, 7)),
-            0x90 => Some(OpCode::new(0x90, BCC, Relative, 2, 2)),
-            0xB0 => Some(OpCode::new(0xB0, BCS, Relative, 2, 2)),
-            0xF0 => Some(OpCode::new(0xF0, BEQ, Relative, 2, 2)),
-            0x24 => Some(OpCode::new(0x24, BIT, ZeroPage, 2, 3)),
-            0x2C => Some(OpCode::new(0x2C, BIT, Absolute, 3, 4)),
-            0x30 => Some(OpCode::new(0x30, BMI, Relative, 2, 2)),
-            0xD0 => Some(OpCode::new(0xD0, BNE, Relative, 2, 2)),
-            0x10 => Some(OpCode::new(0x10, BPL, Relative, 2, 2)),
-            0x00 => Some(OpCode::new(0x00, BRK, Implied, 1, 7)),
-            0x50 => Some(OpCode::new(0x50, BVC, Relative, 2, 2)),
-            0x70 => Some(OpCode::new(0x70, BVS, Relative, 2, 2)),
-            0x18 => Some(OpCode::new(0x18, CLC, Implied, 1, 2)),
-            0xD8 => Some(OpCode::new(0xD8, CLD, Implied, 1, 2)),
-            0x58 => Some(OpCode::new(0x58, CLI, Implied, 1, 2)),
-            0xB8 => Some(OpCode::new(0xB8, CLV, Implied, 1, 2)),
-            0xC9 => Some(OpCode::new(0xC9, CMP, Immediate, 2, 2)),
-            0xC5 => Some(OpCode::new(0xC5, CMP, ZeroPage, 2, 3)),
-            0xD5 => Some(OpCode::new(0xD5, CMP, ZeroPageX, 2, 4)),
-            0xCD => Some(OpCode::new(0xCD, CMP, Absolute, 3, 4)),
-            0xDD => Some(OpCode::new(0xDD, CMP, AbsoluteX, 3, 4)),
-            0xD9 => Some(OpCode::new(0xD9, CMP, AbsoluteY, 3, 4)),
-            0xC1 => Some(OpCode::new(0xC1, CMP, IndirectX, 2, 6)),
-            0xD1 => Some(OpCode::new(0xD1, CMP, IndirectY, 2, 5)),
-            0xE0 => Some(OpCode::new(0xE0, CPX, Immediate, 2, 2)),
-            0xE4 => Some(OpCode::new(0xE4, CPX, ZeroPage, 2, 3)),
-            0xEC => Some(OpCode::new(0xEC, CPX, Absolute, 3, 4)),
-            0xC0 => Some(OpCode::new(0xC0, CPY, Immediate, 2, 2)),
-            0xC4 => Some(OpCode::new(0xC4, CPY, ZeroPage, 2, 3)),
-            0xCC => Some(OpCode::new(0xCC, CPY, Absolute, 3, 4)),
-            0xC6 => Some(OpCode::new(0xC6, DEC, ZeroPage, 2, 5)),
-            0xD6 => Some(OpCode::new(0xD6, DEC, ZeroPageX, 2, 6)),
-            0xCE => Some(OpCode::new(0xCE, DEC, Absolute, 3, 3)),
-            0xDE => Some(OpCode::new(0xDE, DEC, AbsoluteX, 3, 7)),
-            0xCA => Some(OpCode::new(0xCA, DEX, Implied, 1, 2)),
-            0x88 => Some(OpCode::new(0x88, DEY, Implied, 1, 2)),
-            0x49 => Some(OpCode::new(0x49, EOR, Immediate, 2, 2)),
-            0x45 => Some(OpCode::new(0x45, EOR, ZeroPage, 2, 3)),
-            0x55 => Some(OpCode::new(0x55, EOR, ZeroPageX, 2, 4)),
-            0x4D => Some(OpCode::new(0x4D, EOR, Absolute, 3, 4)),
-            0x5D => Some(OpCode::new(0x5D, EOR, AbsoluteX, 3, 4)),
-            0x59 => Some(OpCode::new(0x59, EOR, AbsoluteY, 3, 4)),
-            0x41 => Some(OpCode::new(0x41, EOR, IndirectX, 2, 6)),
-            0x51 => Some(OpCode::new(0x51, EOR, IndirectY, 2, 5)),
-            0xE6 => Some(OpCode::new(0xE6, INC, ZeroPage, 2, 5)),
-            0xF6 => Some(OpCode::new(0xF6, INC, ZeroPageX, 2, 6)),
-            0xEE => Some(OpCode::new(0xEE, INC, Absolute, 3, 6)),
-            0xFE => Some(OpCode::new(0xFE, INC, AbsoluteX, 3, 7)),
-            0xE8 => Some(OpCode::new(0xE8, INX, Implied, 1, 2)),
-            0xC8 => Some(OpCode::new(0xC8, INY, Implied, 1, 2)),
-            0x4C => Some(OpCode::new(0x4C, JMP, Absolute, 3, 3)),
-            0x6C => Some(OpCode::new(0x6C, JMP, Indirect, 3, 5)),
-            0x20 => Some(OpCode::new(0x20, JSR, Absolute, 3, 6)),
-            0xA9 => Some(OpCode::new(0xA9, LDA, Immediate, 2, 2)),
-            0xA5 => Some(OpCode::new(0xA5, LDA, ZeroPage, 2, 3)),
-            0xB5 => Some(OpCode::new(0xB5, LDA, ZeroPageX, 2, 4)),
-            0xAD => Some(OpCode::new(0xAD, LDA, Absolute, 3, 4)),
-            0xBD => Some(OpCode::new(0xBD, LDA, AbsoluteX, 3, 4)),
-            0xB9 => Some(OpCode::new(0xB9, LDA, AbsoluteY, 3, 4)),
-            0xA1 => Some(OpCode::new(0xA1, LDA, IndirectX, 2, 6)),
-            0xB1 => Some(OpCode::new(0xB1, LDA, IndirectY, 2, 5)),
-            0xA2 => Some(OpCode::new(0xA2, LDX, Immediate, 2, 2)),
-            0xA6 => Some(OpCode::new(0xA6, LDX, ZeroPage, 2, 3)),
-            0xB6 => Some(OpCode::new(0xB6, LDX, ZeroPageY, 2, 4)),
-            0xAE => Some(OpCode::new(0xAE, LDX, Absolute, 3, 4)),
-            0xBE => Some(OpCode::new(0xBE, LDX, AbsoluteY, 3, 4)),
-            0xA0 => Some(OpCode::new(0xA0, LDY, Immediate, 2, 2)),
-            0xA4 => Some(OpCode::new(0xA4, LDY, ZeroPage, 2, 3)),
-            0xB4 => Some(OpCode::new(0xB4, LDY, ZeroPageX, 2, 4)),
-            0xAC => Some(OpCode::new(0xAC, LDY, Absolute, 3, 4)),
-            0xBC => Some(OpCode::new(0xBC, LDY, AbsoluteX, 3, 4)),
-            0x4A => Some(OpCode::new(0x4A, LSR, Accumulator, 1, 2)),
-            0x46 => Some(OpCode::new(0x46, LSR, ZeroPage, 2, 5)),
-            0x56 => Some(OpCode::new(0x56, LSR, ZeroPageX, 2, 6)),
-            0x4E => Some(OpCode::new(0x4E, LSR, Absolute, 3, 6)),
-            0x5E => Some(OpCode::new(0x5E, LSR, AbsoluteX, 3, 7)),
-            0xEA => Some(OpCode::new(0xEA, NOP, Implied, 1, 2)),
-            0x09 => Some(OpCode::new(0x09, ORA, Immediate, 2, 2)),
-            0x05 => Some(OpCode::new(0x05, ORA, ZeroPage, 2, 3)),
-            0x15 => Some(OpCode::new(0x15, ORA, ZeroPageX, 2, 4)),
-            0x0D => Some(OpCode::new(0x0D, ORA, Absolute, 3, 4)),
-            0x1D => Some(OpCode::new(0x1D, ORA, AbsoluteX, 3, 4)),
-            0x19 => Some(OpCode::new(0x19, ORA, AbsoluteY, 3, 4)),
-            0x01 => Some(OpCode::new(0x01, ORA, IndirectX, 2, 6)),
-            0x11 => Some(OpCode::new(0x11, ORA, IndirectY, 2, 5)),
-            0x48 => Some(OpCode::new(0x48, PHA, Implied, 1, 3)),
-            0x08 => Some(OpCode::new(0x08, PHP, Implied, 1, 3)),
-            0x68 => Some(OpCode::new(0x68, PLA, Implied, 1, 4)),
-            0x28 => Some(OpCode::new(0x28, PHP, Implied, 1, 4)),
-            0x2A => Some(OpCode::new(0x2A, ROL, Accumulator, 1, 2)),
-            0x26 => Some(OpCode::new(0x26, ROL, ZeroPage, 2, 5)),
-            0x36 => Some(OpCode::new(0x36, ROL, ZeroPageX, 2, 6)),
-            0x2E => Some(OpCode::new(0x2E, ROL, Absolute, 3, 6)),
-            0x3E => Some(OpCode::new(0x3E, ROL, AbsoluteX, 3, 7)),
-            0x6A => Some(OpCode::new(0x6A, ROR, Accumulator, 1, 2)),
-            0x66 => Some(OpCode::new(0x66, ROR, ZeroPage, 2, 5)),
-            0x76 => Some(OpCode::new(0x76, ROR, ZeroPageX, 2, 6)),
-            0x6E => Some(OpCode::new(0x6E, ROR, Absolute, 3, 6)),
-            0x7E => Some(OpCode::new(0x7E, ROR, AbsoluteX, 3, 7)),
-            0x40 => Some(OpCode::new(0x40, RTI, Implied, 1, 6)),
-            0x60 => Some(OpCode::new(0x60, RTS, Implied, 1, 6)),
-            0xE9 => Some(OpCode::new(0xE9, SBC, Immediate, 2, 2)),
-            0xE5 => Some(OpCode::new(0xE5, SBC, ZeroPage, 2, 3)),
-            0xF5 => Some(OpCode::new(0xF5, SBC, ZeroPageX, 2, 4)),
-            0xED => Some(OpCode::new(0xED, SBC, Absolute, 3, 4)),
-            0xFD => Some(OpCode::new(0xFD, SBC, AbsoluteX, 3, 4)),
-            0xF9 => Some(OpCode::new(0xF9, SBC, AbsoluteY, 3, 4)),
-            0xE1 => Some(OpCode::new(0xE1, SBC, IndirectX, 2, 6)),
-            0xF1 => Some(OpCode::new(0xF1, SBC, IndirectY, 2, 5)),
-            0x38 => Some(OpCode::new(0x38, SEC, Implied, 1, 2)),
-            0xF8 => Some(OpCode::new(0xF8, SED, Implied, 1, 2)),
-            0x78 => Some(OpCode::new(0x78, SEI, Implied, 1, 2)),
-            0x85 => Some(OpCode::new(0x85, STA, ZeroPage, 2, 3)),
-            0x95 => Some(OpCode::new(0x95, STA, ZeroPageX, 2, 4)),
-            0x8D => Some(OpCode::new(0x8D, STA, Absolute, 3, 4)),
-            0x9D => Some(OpCode::new(0x9D, STA, AbsoluteX, 3, 5)),
-            0x99 => Some(OpCode::new(0x99, STA, AbsoluteY, 3, 5)),
-            0x81 => Some(OpCode::new(0x81, STA, IndirectX, 2, 6)),
-            0x91 => Some(OpCode::new(0x91, STA, IndirectY, 2, 6)),
-            0x86 => Some(OpCode::new(0x86, STX, ZeroPage, 2, 3)),
-            0x96 => Some(OpCode::new(0x96, STX, ZeroPageY, 2, 4)),
-            0x8E => Some(OpCode::new(0x8E, STX, Absolute, 3, 4)),
-            0x84 => Some(OpCode::new(0x84, STY, ZeroPage, 2, 3)),
-            0x94 => Some(OpCode::new(0x94, STY, ZeroPageX, 2, 4)),
-            0x8C => Some(OpCode::new(0x8C, STY, Absolute, 3, 4)),
-            0xAA => Some(OpCode::new(0xAA, TAX, Implied, 1, 2)),
-            0xA8 => Some(OpCode::new(0xA8, TAY, Implied, 1, 2)),
-            0xBA => Some(OpCode::new(0xBA, TSX, Implied, 1, 2)),
-            0x8A => Some(OpCode::new(0x8A, TXA, Implied, 1, 2)),
-            0x9A => Some(OpCode::new(0x9A, TXS, Implied, 1, 2)),
-            0x98 => Some(OpCode::new(0x98, TYA, Implied, 1, 2)),
-            _ => None,
+    /// The bit tested or changed by RMB, SMB, BBR and BBS, encoded in the
+    /// opcode's high nibble.
+    pub fn bit(&self) -> u8 {
+        (self.code >> 4) & 7
+    }
+}
+
+/// Declares the instruction set. Each mnemonic becomes an `OpId` variant and
+/// each row under it an encoding that `OpCode::get` decodes, so adding an
+/// instruction is one row here plus a handler in `Cpu::execute`. Rows for
+/// undocumented NMOS encodings end in an `undocumented` tag and rows only
+/// present on the 65C02 in a `cmos` tag. The 65C02's reserved bytes, which
+/// it executes as NOPs, carry both. Undocumented NMOS encodings with no
+/// reliable behavior are tagged `unstable` instead.
+macro_rules! opcodes {
+    ($($id:ident {
+        $($code:literal => $addressing:ident, $bytes:expr, $cycles:expr $(, $tag:ident)*;)*
+    })*) => {
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        pub enum OpId {
+            $($id,)*
+            INVALID,
+        }
+        
+        impl OpCode {
+            /// Looks up an NMOS encoding; see `get_for`.
+            pub fn get(code: u8) -> Option<OpCode> {
+                OpCode::get_for(Variant::Nmos, code)
+            }
+            
+            /// Looks up one of the 151 documented encodings, or an encoding
+            /// specific to `variant`. Every byte has a row on every variant,
+            /// so `None` only means the byte is undefined there; bytes the CPU
+            /// won't run are still found, marked `stable: false`. An opcode the
+            /// CPU can't execute yet is also found here and rejected later by
+            /// `Cpu::execute`.
+            pub fn get_for(variant: Variant, code: u8) -> Option<OpCode> {
+                use self::Addressing::*;
+                match code {
+                    $($($code if available!(variant $(, $tag)*) => Some(OpCode::new(
+                        $code, OpId::$id, $addressing, $bytes, $cycles,
+                        documented!($($tag)*), stable!($($tag)*),
+                    )),)*)*
+                    _ => None,
+                }
+            }
         }
     }
 }
 
+macro_rules! documented {
+    () => { true };
+    (undocumented) => { false };
+    (cmos) => { true };
+    (cmos undocumented) => { false };
+    (unstable) => { false };
+}
+
+macro_rules! stable {
+    (unstable) => { false };
+    ($($tag:ident)*) => { true };
+}
+
+macro_rules! available {
+    ($variant:expr) => { true };
+    ($variant:expr, undocumented) => { $variant != Variant::Cmos };
+    ($variant:expr, cmos) => { $variant == Variant::Cmos };
+    ($variant:expr, cmos, undocumented) => { $variant == Variant::Cmos };
+    ($variant:expr, unstable) => { $variant != Variant::Cmos };
+}
+
+opcodes! {
+    ADC {
+        0x69 => Immediate, 2, 2;
+        0x65 => ZeroPage, 2, 3;
+        0x75 => ZeroPageX, 2, 4;
+        0x6D => Absolute, 3, 4;
+        0x7D => AbsoluteX, 3, 4;
+        0x79 => AbsoluteY, 3, 4;
+        0x61 => IndirectX, 2, 6;
+        0x71 => IndirectY, 2, 5;
+        0x72 => ZeroPageIndirect, 2, 5, cmos;
+    }
+    ALR {
+        0x4B => Immediate, 2, 2, undocumented;
+    }
+    ANC {
+        0x0B => Immediate, 2, 2, undocumented;
+        0x2B => Immediate, 2, 2, undocumented;
+    }
+    AND {
+        0x29 => Immediate, 2, 2;
+        0x25 => ZeroPage, 2, 3;
+        0x35 => ZeroPageX, 2, 4;
+        0x2D => Absolute, 3, 4;
+        0x3D => AbsoluteX, 3, 4;
+        0x39 => AbsoluteY, 3, 4;
+        0x21 => IndirectX, 2, 6;
+        0x31 => IndirectY, 2, 5;
+        0x32 => ZeroPageIndirect, 2, 5, cmos;
+    }
+    ANE {
+        0x8B => Immediate, 2, 2, unstable;
+    }
+    ARR {
+        0x6B => Immediate, 2, 2, undocumented;
+    }
+    ASL {
+        0x0A => Accumulator, 1, 2;
+        0x06 => ZeroPage, 2, 5;
+        0x16 => ZeroPageX, 2, 6;
+        0x0E => Absolute, 3, 6;
+        0x1E => AbsoluteX, 3, 7;
+    }
+    AXS {
+        0xCB => Immediate, 2, 2, undocumented;
+    }
+    BBR {
+        0x0F => ZeroPageRelative, 3, 5, cmos;
+        0x1F => ZeroPageRelative, 3, 5, cmos;
+        0x2F => ZeroPageRelative, 3, 5, cmos;
+        0x3F => ZeroPageRelative, 3, 5, cmos;
+        0x4F => ZeroPageRelative, 3, 5, cmos;
+        0x5F => ZeroPageRelative, 3, 5, cmos;
+        0x6F => ZeroPageRelative, 3, 5, cmos;
+        0x7F => ZeroPageRelative, 3, 5, cmos;
+    }
+    BBS {
+        0x8F => ZeroPageRelative, 3, 5, cmos;
+        0x9F => ZeroPageRelative, 3, 5, cmos;
+        0xAF => ZeroPageRelative, 3, 5, cmos;
+        0xBF => ZeroPageRelative, 3, 5, cmos;
+        0xCF => ZeroPageRelative, 3, 5, cmos;
+        0xDF => ZeroPageRelative, 3, 5, cmos;
+        0xEF => ZeroPageRelative, 3, 5, cmos;
+        0xFF => ZeroPageRelative, 3, 5, cmos;
+    }
+    BCC {
+        0x90 => Relative, 2, 2;
+    }
+    BCS {
+        0xB0 => Relative, 2, 2;
+    }
+    BEQ {
+        0xF0 => Relative, 2, 2;
+    }
+    BIT {
+        0x24 => ZeroPage, 2, 3;
+        0x2C => Absolute, 3, 4;
+        0x89 => Immediate, 2, 2, cmos;
+        0x34 => ZeroPageX, 2, 4, cmos;
+        0x3C => AbsoluteX, 3, 4, cmos;
+    }
+    BMI {
+        0x30 => Relative, 2, 2;
+    }
+    BNE {
+        0xD0 => Relative, 2, 2;
+    }
+    BPL {
+        0x10 => Relative, 2, 2;
+    }
+    BRA {
+        0x80 => Relative, 2, 2, cmos;
+    }
+    BRK {
+        // The second byte is a signature the CPU skips over
+        0x00 => Immediate, 2, 7;
+    }
+    BVC {
+        0x50 => Relative, 2, 2;
+    }
+    BVS {
+        0x70 => Relative, 2, 2;
+    }
+    CLC {
+        0x18 => Implied, 1, 2;
+    }
+    CLD {
+        0xD8 => Implied, 1, 2;
+    }
+    CLI {
+        0x58 => Implied, 1, 2;
+    }
+    CLV {
+        0xB8 => Implied, 1, 2;
+    }
+    CMP {
+        0xC9 => Immediate, 2, 2;
+        0xC5 => ZeroPage, 2, 3;
+        0xD5 => ZeroPageX, 2, 4;
+        0xCD => Absolute, 3, 4;
+        0xDD => AbsoluteX, 3, 4;
+        0xD9 => AbsoluteY, 3, 4;
+        0xC1 => IndirectX, 2, 6;
+        0xD1 => IndirectY, 2, 5;
+        0xD2 => ZeroPageIndirect, 2, 5, cmos;
+    }
+    CPX {
+        0xE0 => Immediate, 2, 2;
+        0xE4 => ZeroPage, 2, 3;
+        0xEC => Absolute, 3, 4;
+    }
+    CPY {
+        0xC0 => Immediate, 2, 2;
+        0xC4 => ZeroPage, 2, 3;
+        0xCC => Absolute, 3, 4;
+    }
+    DCP {
+        0xC7 => ZeroPage, 2, 5, undocumented;
+        0xD7 => ZeroPageX, 2, 6, undocumented;
+        0xCF => Absolute, 3, 6, undocumented;
+        0xDF => AbsoluteX, 3, 7, undocumented;
+        0xDB => AbsoluteY, 3, 7, undocumented;
+        0xC3 => IndirectX, 2, 8, undocumented;
+        0xD3 => IndirectY, 2, 8, undocumented;
+    }
+    DEC {
+        0xC6 => ZeroPage, 2, 5;
+        0xD6 => ZeroPageX, 2, 6;
+        0xCE => Absolute, 3, 6;
+        0xDE => AbsoluteX, 3, 7;
+        0x3A => Accumulator, 1, 2, cmos;
+    }
+    DEX {
+        0xCA => Implied, 1, 2;
+    }
+    DEY {
+        0x88 => Implied, 1, 2;
+    }
+    EOR {
+        0x49 => Immediate, 2, 2;
+        0x45 => ZeroPage, 2, 3;
+        0x55 => ZeroPageX, 2, 4;
+        0x4D => Absolute, 3, 4;
+        0x5D => AbsoluteX, 3, 4;
+        0x59 => AbsoluteY, 3, 4;
+        0x41 => IndirectX, 2, 6;
+        0x51 => IndirectY, 2, 5;
+        0x52 => ZeroPageIndirect, 2, 5, cmos;
+    }
+    INC {
+        0xE6 => ZeroPage, 2, 5;
+        0xF6 => ZeroPageX, 2, 6;
+        0xEE => Absolute, 3, 6;
+        0xFE => AbsoluteX, 3, 7;
+        0x1A => Accumulator, 1, 2, cmos;
+    }
+    INX {
+        0xE8 => Implied, 1, 2;
+    }
+    INY {
+        0xC8 => Implied, 1, 2;
+    }
+    ISC {
+        0xE7 => ZeroPage, 2, 5, undocumented;
+        0xF7 => ZeroPageX, 2, 6, undocumented;
+        0xEF => Absolute, 3, 6, undocumented;
+        0xFF => AbsoluteX, 3, 7, undocumented;
+        0xFB => AbsoluteY, 3, 7, undocumented;
+        0xE3 => IndirectX, 2, 8, undocumented;
+        0xF3 => IndirectY, 2, 8, undocumented;
+    }
+    JMP {
+        0x4C => Absolute, 3, 3;
+        0x6C => Indirect, 3, 5;
+        0x7C => AbsoluteIndirectX, 3, 6, cmos;
+    }
+    JSR {
+        0x20 => Absolute, 3, 6;
+    }
+    KIL {
+        0x02 => Implied, 1, 2, undocumented;
+        0x12 => Implied, 1, 2, undocumented;
+        0x22 => Implied, 1, 2, undocumented;
+        0x32 => Implied, 1, 2, undocumented;
+        0x42 => Implied, 1, 2, undocumented;
+        0x52 => Implied, 1, 2, undocumented;
+        0x62 => Implied, 1, 2, undocumented;
+        0x72 => Implied, 1, 2, undocumented;
+        0x92 => Implied, 1, 2, undocumented;
+        0xB2 => Implied, 1, 2, undocumented;
+        0xD2 => Implied, 1, 2, undocumented;
+        0xF2 => Implied, 1, 2, undocumented;
+    }
+    LAS {
+        0xBB => AbsoluteY, 3, 4, unstable;
+    }
+    LAX {
+        0xA7 => ZeroPage, 2, 3, undocumented;
+        0xB7 => ZeroPageY, 2, 4, undocumented;
+        0xAF => Absolute, 3, 4, undocumented;
+        0xBF => AbsoluteY, 3, 4, undocumented;
+        0xA3 => IndirectX, 2, 6, undocumented;
+        0xB3 => IndirectY, 2, 5, undocumented;
+    }
+    LDA {
+        0xA9 => Immediate, 2, 2;
+        0xA5 => ZeroPage, 2, 3;
+        0xB5 => ZeroPageX, 2, 4;
+        0xAD => Absolute, 3, 4;
+        0xBD => AbsoluteX, 3, 4;
+        0xB9 => AbsoluteY, 3, 4;
+        0xA1 => IndirectX, 2, 6;
+        0xB1 => IndirectY, 2, 5;
+        0xB2 => ZeroPageIndirect, 2, 5, cmos;
+    }
+    LDX {
+        0xA2 => Immediate, 2, 2;
+        0xA6 => ZeroPage, 2, 3;
+        0xB6 => ZeroPageY, 2, 4;
+        0xAE => Absolute, 3, 4;
+        0xBE => AbsoluteY, 3, 4;
+    }
+    LDY {
+        0xA0 => Immediate, 2, 2;
+        0xA4 => ZeroPage, 2, 3;
+        0xB4 => ZeroPageX, 2, 4;
+        0xAC => Absolute, 3, 4;
+        0xBC => AbsoluteX, 3, 4;
+    }
+    LSR {
+        0x4A => Accumulator, 1, 2;
+        0x46 => ZeroPage, 2, 5;
+        0x56 => ZeroPageX, 2, 6;
+        0x4E => Absolute, 3, 6;
+        0x5E => AbsoluteX, 3, 7;
+    }
+    LXA {
+        0xAB => Immediate, 2, 2, unstable;
+    }
+    NOP {
+        0xEA => Implied, 1, 2;
+        0x1A => Implied, 1, 2, undocumented;
+        0x3A => Implied, 1, 2, undocumented;
+        0x5A => Implied, 1, 2, undocumented;
+        0x7A => Implied, 1, 2, undocumented;
+        0xDA => Implied, 1, 2, undocumented;
+        0xFA => Implied, 1, 2, undocumented;
+        0x80 => Immediate, 2, 2, undocumented;
+        0x82 => Immediate, 2, 2, undocumented;
+        0x89 => Immediate, 2, 2, undocumented;
+        0xC2 => Immediate, 2, 2, undocumented;
+        0xE2 => Immediate, 2, 2, undocumented;
+        0x04 => ZeroPage, 2, 3, undocumented;
+        0x44 => ZeroPage, 2, 3, undocumented;
+        0x64 => ZeroPage, 2, 3, undocumented;
+        0x14 => ZeroPageX, 2, 4, undocumented;
+        0x34 => ZeroPageX, 2, 4, undocumented;
+        0x54 => ZeroPageX, 2, 4, undocumented;
+        0x74 => ZeroPageX, 2, 4, undocumented;
+        0xD4 => ZeroPageX, 2, 4, undocumented;
+        0xF4 => ZeroPageX, 2, 4, undocumented;
+        0x0C => Absolute, 3, 4, undocumented;
+        0x1C => AbsoluteX, 3, 4, undocumented;
+        0x3C => AbsoluteX, 3, 4, undocumented;
+        0x5C => AbsoluteX, 3, 4, undocumented;
+        0x7C => AbsoluteX, 3, 4, undocumented;
+        0xDC => AbsoluteX, 3, 4, undocumented;
+        0xFC => AbsoluteX, 3, 4, undocumented;
+        0x03 => Implied, 1, 1, cmos, undocumented;
+        0x13 => Implied, 1, 1, cmos, undocumented;
+        0x23 => Implied, 1, 1, cmos, undocumented;
+        0x33 => Implied, 1, 1, cmos, undocumented;
+        0x43 => Implied, 1, 1, cmos, undocumented;
+        0x53 => Implied, 1, 1, cmos, undocumented;
+        0x63 => Implied, 1, 1, cmos, undocumented;
+        0x73 => Implied, 1, 1, cmos, undocumented;
+        0x83 => Implied, 1, 1, cmos, undocumented;
+        0x93 => Implied, 1, 1, cmos, undocumented;
+        0xA3 => Implied, 1, 1, cmos, undocumented;
+        0xB3 => Implied, 1, 1, cmos, undocumented;
+        0xC3 => Implied, 1, 1, cmos, undocumented;
+        0xD3 => Implied, 1, 1, cmos, undocumented;
+        0xE3 => Implied, 1, 1, cmos, undocumented;
+        0xF3 => Implied, 1, 1, cmos, undocumented;
+        0x0B => Implied, 1, 1, cmos, undocumented;
+        0x1B => Implied, 1, 1, cmos, undocumented;
+        0x2B => Implied, 1, 1, cmos, undocumented;
+        0x3B => Implied, 1, 1, cmos, undocumented;
+        0x4B => Implied, 1, 1, cmos, undocumented;
+        0x5B => Implied, 1, 1, cmos, undocumented;
+        0x6B => Implied, 1, 1, cmos, undocumented;
+        0x7B => Implied, 1, 1, cmos, undocumented;
+        0x8B => Implied, 1, 1, cmos, undocumented;
+        0x9B => Implied, 1, 1, cmos, undocumented;
+        0xAB => Implied, 1, 1, cmos, undocumented;
+        0xBB => Implied, 1, 1, cmos, undocumented;
+        0xEB => Implied, 1, 1, cmos, undocumented;
+        0xFB => Implied, 1, 1, cmos, undocumented;
+        0x02 => Immediate, 2, 2, cmos, undocumented;
+        0x22 => Immediate, 2, 2, cmos, undocumented;
+        0x42 => Immediate, 2, 2, cmos, undocumented;
+        0x62 => Immediate, 2, 2, cmos, undocumented;
+        0x82 => Immediate, 2, 2, cmos, undocumented;
+        0xC2 => Immediate, 2, 2, cmos, undocumented;
+        0xE2 => Immediate, 2, 2, cmos, undocumented;
+        0x44 => ZeroPage, 2, 3, cmos, undocumented;
+        0x54 => ZeroPageX, 2, 4, cmos, undocumented;
+        0xD4 => ZeroPageX, 2, 4, cmos, undocumented;
+        0xF4 => ZeroPageX, 2, 4, cmos, undocumented;
+        0x5C => Absolute, 3, 8, cmos, undocumented;
+        0xDC => Absolute, 3, 4, cmos, undocumented;
+        0xFC => Absolute, 3, 4, cmos, undocumented;
+    }
+    ORA {
+        0x09 => Immediate, 2, 2;
+        0x05 => ZeroPage, 2, 3;
+        0x15 => ZeroPageX, 2, 4;
+        0x0D => Absolute, 3, 4;
+        0x1D => AbsoluteX, 3, 4;
+        0x19 => AbsoluteY, 3, 4;
+        0x01 => IndirectX, 2, 6;
+        0x11 => IndirectY, 2, 5;
+        0x12 => ZeroPageIndirect, 2, 5, cmos;
+    }
+    PHA {
+        0x48 => Implied, 1, 3;
+    }
+    PHP {
+        0x08 => Implied, 1, 3;
+    }
+    PHX {
+        0xDA => Implied, 1, 3, cmos;
+    }
+    PHY {
+        0x5A => Implied, 1, 3, cmos;
+    }
+    PLA {
+        0x68 => Implied, 1, 4;
+    }
+    PLP {
+        0x28 => Implied, 1, 4;
+    }
+    PLX {
+        0xFA => Implied, 1, 4, cmos;
+    }
+    PLY {
+        0x7A => Implied, 1, 4, cmos;
+    }
+    RLA {
+        0x27 => ZeroPage, 2, 5, undocumented;
+        0x37 => ZeroPageX, 2, 6, undocumented;
+        0x2F => Absolute, 3, 6, undocumented;
+        0x3F => AbsoluteX, 3, 7, undocumented;
+        0x3B => AbsoluteY, 3, 7, undocumented;
+        0x23 => IndirectX, 2, 8, undocumented;
+        0x33 => IndirectY, 2, 8, undocumented;
+    }
+    RMB {
+        0x07 => ZeroPage, 2, 5, cmos;
+        0x17 => ZeroPage, 2, 5, cmos;
+        0x27 => ZeroPage, 2, 5, cmos;
+        0x37 => ZeroPage, 2, 5, cmos;
+        0x47 => ZeroPage, 2, 5, cmos;
+        0x57 => ZeroPage, 2, 5, cmos;
+        0x67 => ZeroPage, 2, 5, cmos;
+        0x77 => ZeroPage, 2, 5, cmos;
+    }
+    ROL {
+        0x2A => Accumulator, 1, 2;
+        0x26 => ZeroPage, 2, 5;
+        0x36 => ZeroPageX, 2, 6;
+        0x2E => Absolute, 3, 6;
+        0x3E => AbsoluteX, 3, 7;
+    }
+    ROR {
+        0x6A => Accumulator, 1, 2;
+        0x66 => ZeroPage, 2, 5;
+        0x76 => ZeroPageX, 2, 6;
+        0x6E => Absolute, 3, 6;
+        0x7E => AbsoluteX, 3, 7;
+    }
+    RRA {
+        0x67 => ZeroPage, 2, 5, undocumented;
+        0x77 => ZeroPageX, 2, 6, undocumented;
+        0x6F => Absolute, 3, 6, undocumented;
+        0x7F => AbsoluteX, 3, 7, undocumented;
+        0x7B => AbsoluteY, 3, 7, undocumented;
+        0x63 => IndirectX, 2, 8, undocumented;
+        0x73 => IndirectY, 2, 8, undocumented;
+    }
+    RTI {
+        0x40 => Implied, 1, 6;
+    }
+    RTS {
+        0x60 => Implied, 1, 6;
+    }
+    SAX {
+        0x87 => ZeroPage, 2, 3, undocumented;
+        0x97 => ZeroPageY, 2, 4, undocumented;
+        0x8F => Absolute, 3, 4, undocumented;
+        0x83 => IndirectX, 2, 6, undocumented;
+    }
+    SBC {
+        0xE9 => Immediate, 2, 2;
+        0xE5 => ZeroPage, 2, 3;
+        0xF5 => ZeroPageX, 2, 4;
+        0xED => Absolute, 3, 4;
+        0xFD => AbsoluteX, 3, 4;
+        0xF9 => AbsoluteY, 3, 4;
+        0xE1 => IndirectX, 2, 6;
+        0xF1 => IndirectY, 2, 5;
+        0xF2 => ZeroPageIndirect, 2, 5, cmos;
+        0xEB => Immediate, 2, 2, undocumented;
+    }
+    SEC {
+        0x38 => Implied, 1, 2;
+    }
+    SED {
+        0xF8 => Implied, 1, 2;
+    }
+    SEI {
+        0x78 => Implied, 1, 2;
+    }
+    SHA {
+        0x93 => IndirectY, 2, 6, unstable;
+        0x9F => AbsoluteY, 3, 5, unstable;
+    }
+    SHX {
+        0x9E => AbsoluteY, 3, 5, unstable;
+    }
+    SHY {
+        0x9C => AbsoluteX, 3, 5, unstable;
+    }
+    SLO {
+        0x07 => ZeroPage, 2, 5, undocumented;
+        0x17 => ZeroPageX, 2, 6, undocumented;
+        0x0F => Absolute, 3, 6, undocumented;
+        0x1F => AbsoluteX, 3, 7, undocumented;
+        0x1B => AbsoluteY, 3, 7, undocumented;
+        0x03 => IndirectX, 2, 8, undocumented;
+        0x13 => IndirectY, 2, 8, undocumented;
+    }
+    SMB {
+        0x87 => ZeroPage, 2, 5, cmos;
+        0x97 => ZeroPage, 2, 5, cmos;
+        0xA7 => ZeroPage, 2, 5, cmos;
+        0xB7 => ZeroPage, 2, 5, cmos;
+        0xC7 => ZeroPage, 2, 5, cmos;
+        0xD7 => ZeroPage, 2, 5, cmos;
+        0xE7 => ZeroPage, 2, 5, cmos;
+        0xF7 => ZeroPage, 2, 5, cmos;
+    }
+    SRE {
+        0x47 => ZeroPage, 2, 5, undocumented;
+        0x57 => ZeroPageX, 2, 6, undocumented;
+        0x4F => Absolute, 3, 6, undocumented;
+        0x5F => AbsoluteX, 3, 7, undocumented;
+        0x5B => AbsoluteY, 3, 7, undocumented;
+        0x43 => IndirectX, 2, 8, undocumented;
+        0x53 => IndirectY, 2, 8, undocumented;
+    }
+    STA {
+        0x85 => ZeroPage, 2, 3;
+        0x95 => ZeroPageX, 2, 4;
+        0x8D => Absolute, 3, 4;
+        0x9D => AbsoluteX, 3, 5;
+        0x99 => AbsoluteY, 3, 5;
+        0x81 => IndirectX, 2, 6;
+        0x91 => IndirectY, 2, 6;
+        0x92 => ZeroPageIndirect, 2, 5, cmos;
+    }
+    STP {
+        0xDB => Implied, 1, 3, cmos;
+    }
+    STX {
+        0x86 => ZeroPage, 2, 3;
+        0x96 => ZeroPageY, 2, 4;
+        0x8E => Absolute, 3, 4;
+    }
+    STY {
+        0x84 => ZeroPage, 2, 3;
+        0x94 => ZeroPageX, 2, 4;
+        0x8C => Absolute, 3, 4;
+    }
+    STZ {
+        0x64 => ZeroPage, 2, 3, cmos;
+        0x74 => ZeroPageX, 2, 4, cmos;
+        0x9C => Absolute, 3, 4, cmos;
+        0x9E => AbsoluteX, 3, 5, cmos;
+    }
+    TAS {
+        0x9B => AbsoluteY, 3, 5, unstable;
+    }
+    TAX {
+        0xAA => Implied, 1, 2;
+    }
+    TAY {
+        0xA8 => Implied, 1, 2;
+    }
+    TRB {
+        0x14 => ZeroPage, 2, 5, cmos;
+        0x1C => Absolute, 3, 6, cmos;
+    }
+    TSB {
+        0x04 => ZeroPage, 2, 5, cmos;
+        0x0C => Absolute, 3, 6, cmos;
+    }
+    TSX {
+        0xBA => Implied, 1, 2;
+    }
+    TXA {
+        0x8A => Implied, 1, 2;
+    }
+    TXS {
+        0x9A => Implied, 1, 2;
+    }
+    TYA {
+        0x98 => Implied, 1, 2;
+    }
+    WAI {
+        0xCB => Implied, 1, 3, cmos;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Addressing::*;
+    
+    /// The documented NMOS instruction set as listed in the MOS programming
+    /// manual: opcode, mnemonic, addressing mode, bytes and base cycles.
+    const DOCUMENTED: &[(u8, &str, Addressing, u8, u8)] = &[
+        (0x69, "ADC", Immediate, 2, 2),
+        (0x65, "ADC", ZeroPage, 2, 3),
+        (0x75, "ADC", ZeroPageX, 2, 4),
+        (0x6D, "ADC", Absolute, 3, 4),
+        (0x7D, "ADC", AbsoluteX, 3, 4),
+        (0x79, "ADC", AbsoluteY, 3, 4),
+        (0x61, "ADC", IndirectX, 2, 6),
+        (0x71, "ADC", IndirectY, 2, 5),
+        (0x29, "AND", Immediate, 2, 2),
+        (0x25, "AND", ZeroPage, 2, 3),
+        (0x35, "AND", ZeroPageX, 2, 4),
+        (0x2D, "AND", Absolute, 3, 4),
+        (0x3D, "AND", AbsoluteX, 3, 4),
+        (0x39, "AND", AbsoluteY, 3, 4),
+        (0x21, "AND", IndirectX, 2, 6),
+        (0x31, "AND", IndirectY, 2, 5),
+        (0x0A, "ASL", Accumulator, 1, 2),
+        (0x06, "ASL", ZeroPage, 2, 5),
+        (0x16, "ASL", ZeroPageX, 2, 6),
+        (0x0E, "ASL", Absolute, 3, 6),
+        (0x1E, "ASL", AbsoluteX, 3, 7),
+        (0x90, "BCC", Relative, 2, 2),
+        (0xB0, "BCS", Relative, 2, 2),
+        (0xF0, "BEQ", Relative, 2, 2),
+        (0x24, "BIT", ZeroPage, 2, 3),
+        (0x2C, "BIT", Absolute, 3, 4),
+        (0x30, "BMI", Relative, 2, 2),
+        (0xD0, "BNE", Relative, 2, 2),
+        (0x10, "BPL", Relative, 2, 2),
+        // Listed as one byte, but the return address BRK pushes skips two.
+        (0x00, "BRK", Immediate, 2, 7),
+        (0x50, "BVC", Relative, 2, 2),
+        (0x70, "BVS", Relative, 2, 2),
+        (0x18, "CLC", Implied, 1, 2),
+        (0xD8, "CLD", Implied, 1, 2),
+        (0x58, "CLI", Implied, 1, 2),
+        (0xB8, "CLV", Implied, 1, 2),
+        (0xC9, "CMP", Immediate, 2, 2),
+        (0xC5, "CMP", ZeroPage, 2, 3),
+        (0xD5, "CMP", ZeroPageX, 2, 4),
+        (0xCD, "CMP", Absolute, 3, 4),
+        (0xDD, "CMP", AbsoluteX, 3, 4),
+        (0xD9, "CMP", AbsoluteY, 3, 4),
+        (0xC1, "CMP", IndirectX, 2, 6),
+        (0xD1, "CMP", IndirectY, 2, 5),
+        (0xE0, "CPX", Immediate, 2, 2),
+        (0xE4, "CPX", ZeroPage, 2, 3),
+        (0xEC, "CPX", Absolute, 3, 4),
+        (0xC0, "CPY", Immediate, 2, 2),
+        (0xC4, "CPY", ZeroPage, 2, 3),
+        (0xCC, "CPY", Absolute, 3, 4),
+        (0xC6, "DEC", ZeroPage, 2, 5),
+        (0xD6, "DEC", ZeroPageX, 2, 6),
+        (0xCE, "DEC", Absolute, 3, 6),
+        (0xDE, "DEC", AbsoluteX, 3, 7),
+        (0xCA, "DEX", Implied, 1, 2),
+        (0x88, "DEY", Implied, 1, 2),
+        (0x49, "EOR", Immediate, 2, 2),
+        (0x45, "EOR", ZeroPage, 2, 3),
+        (0x55, "EOR", ZeroPageX, 2, 4),
+        (0x4D, "EOR", Absolute, 3, 4),
+        (0x5D, "EOR", AbsoluteX, 3, 4),
+        (0x59, "EOR", AbsoluteY, 3, 4),
+        (0x41, "EOR", IndirectX, 2, 6),
+        (0x51, "EOR", IndirectY, 2, 5),
+        (0xE6, "INC", ZeroPage, 2, 5),
+        (0xF6, "INC", ZeroPageX, 2, 6),
+        (0xEE, "INC", Absolute, 3, 6),
+        (0xFE, "INC", AbsoluteX, 3, 7),
+        (0xE8, "INX", Implied, 1, 2),
+        (0xC8, "INY", Implied, 1, 2),
+        (0x4C, "JMP", Absolute, 3, 3),
+        (0x6C, "JMP", Indirect, 3, 5),
+        (0x20, "JSR", Absolute, 3, 6),
+        (0xA9, "LDA", Immediate, 2, 2),
+        (0xA5, "LDA", ZeroPage, 2, 3),
+        (0xB5, "LDA", ZeroPageX, 2, 4),
+        (0xAD, "LDA", Absolute, 3, 4),
+        (0xBD, "LDA", AbsoluteX, 3, 4),
+        (0xB9, "LDA", AbsoluteY, 3, 4),
+        (0xA1, "LDA", IndirectX, 2, 6),
+        (0xB1, "LDA", IndirectY, 2, 5),
+        (0xA2, "LDX", Immediate, 2, 2),
+        (0xA6, "LDX", ZeroPage, 2, 3),
+        (0xB6, "LDX", ZeroPageY, 2, 4),
+        (0xAE, "LDX", Absolute, 3, 4),
+        (0xBE, "LDX", AbsoluteY, 3, 4),
+        (0xA0, "LDY", Immediate, 2, 2),
+        (0xA4, "LDY", ZeroPage, 2, 3),
+        (0xB4, "LDY", ZeroPageX, 2, 4),
+        (0xAC, "LDY", Absolute, 3, 4),
+        (0xBC, "LDY", AbsoluteX, 3, 4),
+        (0x4A, "LSR", Accumulator, 1, 2),
+        (0x46, "LSR", ZeroPage, 2, 5),
+        (0x56, "LSR", ZeroPageX, 2, 6),
+        (0x4E, "LSR", Absolute, 3, 6),
+        (0x5E, "LSR", AbsoluteX, 3, 7),
+        (0xEA, "NOP", Implied, 1, 2),
+        (0x09, "ORA", Immediate, 2, 2),
+        (0x05, "ORA", ZeroPage, 2, 3),
+        (0x15, "ORA", ZeroPageX, 2, 4),
+        (0x0D, "ORA", Absolute, 3, 4),
+        (0x1D, "ORA", AbsoluteX, 3, 4),
+        (0x19, "ORA", AbsoluteY, 3, 4),
+        (0x01, "ORA", IndirectX, 2, 6),
+        (0x11, "ORA", IndirectY, 2, 5),
+        (0x48, "PHA", Implied, 1, 3),
+        (0x08, "PHP", Implied, 1, 3),
+        (0x68, "PLA", Implied, 1, 4),
+        (0x28, "PLP", Implied, 1, 4),
+        (0x2A, "ROL", Accumulator, 1, 2),
+        (0x26, "ROL", ZeroPage, 2, 5),
+        (0x36, "ROL", ZeroPageX, 2, 6),
+        (0x2E, "ROL", Absolute, 3, 6),
+        (0x3E, "ROL", AbsoluteX, 3, 7),
+        (0x6A, "ROR", Accumulator, 1, 2),
+        (0x66, "ROR", ZeroPage, 2, 5),
+        (0x76, "ROR", ZeroPageX, 2, 6),
+        (0x6E, "ROR", Absolute, 3, 6),
+        (0x7E, "ROR", AbsoluteX, 3, 7),
+        (0x40, "RTI", Implied, 1, 6),
+        (0x60, "RTS", Implied, 1, 6),
+        (0xE9, "SBC", Immediate, 2, 2),
+        (0xE5, "SBC", ZeroPage, 2, 3),
+        (0xF5, "SBC", ZeroPageX, 2, 4),
+        (0xED, "SBC", Absolute, 3, 4),
+        (0xFD, "SBC", AbsoluteX, 3, 4),
+        (0xF9, "SBC", AbsoluteY, 3, 4),
+        (0xE1, "SBC", IndirectX, 2, 6),
+        (0xF1, "SBC", IndirectY, 2, 5),
+        (0x38, "SEC", Implied, 1, 2),
+        (0xF8, "SED", Implied, 1, 2),
+        (0x78, "SEI", Implied, 1, 2),
+        (0x85, "STA", ZeroPage, 2, 3),
+        (0x95, "STA", ZeroPageX, 2, 4),
+        (0x8D, "STA", Absolute, 3, 4),
+        (0x9D, "STA", AbsoluteX, 3, 5),
+        (0x99, "STA", AbsoluteY, 3, 5),
+        (0x81, "STA", IndirectX, 2, 6),
+        (0x91, "STA", IndirectY, 2, 6),
+        (0x86, "STX", ZeroPage, 2, 3),
+        (0x96, "STX", ZeroPageY, 2, 4),
+        (0x8E, "STX", Absolute, 3, 4),
+        (0x84, "STY", ZeroPage, 2, 3),
+        (0x94, "STY", ZeroPageX, 2, 4),
+        (0x8C, "STY", Absolute, 3, 4),
+        (0xAA, "TAX", Implied, 1, 2),
+        (0xA8, "TAY", Implied, 1, 2),
+        (0xBA, "TSX", Implied, 1, 2),
+        (0x8A, "TXA", Implied, 1, 2),
+        (0x9A, "TXS", Implied, 1, 2),
+        (0x98, "TYA", Implied, 1, 2),
+    ];
+    
+    fn mnemonic(op: &OpCode) -> String {
+        format!("{:?}", op.id)
+    }
+    
+    #[test]
+    fn documented_opcodes_match_reference() {
+        assert_eq!(DOCUMENTED.len(), 151);
+        for &(code, name, addressing, bytes, cycles) in DOCUMENTED {
+            let op = OpCode::get(code).unwrap_or_else(|| panic!("${:02X} {} missing", code, name));
+            assert_eq!(
+                (op.code, mnemonic(&op).as_str(), op.addressing, op.bytes, op.cycles, op.documented),
+                (code, name, addressing, bytes, cycles, true),
+                "${:02X}", code,
+            );
+        }
+    }
+    
+    #[test]
+    fn only_reference_opcodes_are_documented() {
+        let documented = (0..=0xFFu8)
+            .filter_map(OpCode::get)
+            .filter(|op| op.documented)
+            .count();
+        assert_eq!(documented, DOCUMENTED.len());
+    }
+    
+    #[test]
+    fn documented_opcodes_decode_on_every_variant() {
+        for &variant in &[Variant::Nmos, Variant::Cmos, Variant::Rp2A03] {
+            for &(code, name, addressing, ..) in DOCUMENTED {
+                let op = OpCode::get_for(variant, code).unwrap();
+                assert_eq!((mnemonic(&op).as_str(), op.addressing), (name, addressing), "{:?} ${:02X}", variant, code);
+            }
+        }
+    }
+    
+    #[test]
+    fn tagged_rows_are_gated_by_variant() {
+        for code in 0..=0xFFu8 {
+            let nmos = OpCode::get_for(Variant::Nmos, code);
+            let cmos = OpCode::get_for(Variant::Cmos, code);
+            let ricoh = OpCode::get_for(Variant::Rp2A03, code);
+            
+            // The 2A03 decodes exactly like the NMOS part.
+            assert_eq!(nmos, ricoh, "${:02X}", code);
+            
+            // Undocumented rows never decode on the 65C02, though a few of
+            // its reserved NOPs happen to match the NMOS ones.
+            if let Some(op) = nmos.filter(|op| !op.documented && op.id != OpId::NOP) {
+                assert_ne!(cmos, Some(op), "${:02X}", code);
+            }
+            // Every byte is something on the 65C02, undocumented only as a NOP
+            let op = cmos.unwrap_or_else(|| panic!("${:02X} doesn't decode on the 65C02", code));
+            assert!(op.documented || op.id == OpId::NOP, "${:02X} decodes as undocumented on the 65C02", code);
+            
+            // Rows only the 65C02 has don't decode as the same thing on NMOS.
+            if let (Some(op), Some(n)) = (cmos, nmos) {
+                if op.id != n.id {
+                    assert!(!n.documented, "${:02X} {:?} replaces a documented NMOS opcode", code, op.id);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn shared_bytes_decode_per_variant() {
+        let id = |variant, code| OpCode::get_for(variant, code).map(|op| op.id);
+        assert_eq!(id(Variant::Nmos, 0x80), Some(OpId::NOP));
+        assert_eq!(id(Variant::Cmos, 0x80), Some(OpId::BRA));
+        assert_eq!(id(Variant::Nmos, 0x9C), Some(OpId::SHY));
+        assert_eq!(id(Variant::Cmos, 0x9C), Some(OpId::STZ));
+        assert_eq!(id(Variant::Nmos, 0xCB), Some(OpId::AXS));
+        assert_eq!(id(Variant::Cmos, 0xCB), Some(OpId::WAI));
+        assert_eq!(id(Variant::Rp2A03, 0xA7), Some(OpId::LAX));
+        assert_eq!(id(Variant::Cmos, 0xA7), Some(OpId::SMB));
+        assert_eq!(id(Variant::Nmos, 0x0F), Some(OpId::SLO));
+        assert_eq!(id(Variant::Cmos, 0x0F), Some(OpId::BBR));
+        assert_eq!(id(Variant::Cmos, 0xB2), Some(OpId::LDA));
+        assert_eq!(id(Variant::Nmos, 0xB2), Some(OpId::KIL));
+    }
+    
+    #[test]
+    fn unstable_nmos_bytes_are_marked() {
+        let unstable: Vec<u8> = (0..=0xFFu8)
+            .filter(|&code| !OpCode::get(code).unwrap().stable)
+            .collect();
+        assert_eq!(unstable, [0x8B, 0x93, 0x9B, 0x9C, 0x9E, 0x9F, 0xAB, 0xBB]);
+        for &variant in &[Variant::Nmos, Variant::Cmos, Variant::Rp2A03] {
+            for code in 0..=0xFFu8 {
+                let op = OpCode::get_for(variant, code).unwrap_or_else(|| panic!("{:?} ${:02X} undefined", variant, code));
+                assert!(op.stable || (!op.documented && variant != Variant::Cmos), "{:?} ${:02X}", variant, code);
+            }
+        }
+        // $EB is a stable copy of SBC #imm
+        let op = OpCode::get(0xEB).unwrap();
+        assert_eq!((op.id, op.addressing, op.documented, op.stable), (OpId::SBC, Immediate, false, true));
+    }
+}